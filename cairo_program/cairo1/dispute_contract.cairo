@@ -0,0 +1,187 @@
+// The dispute contract of dispute_contract.cairo (Cairo 0), in Cairo 1 : the same functions, the
+// storage addresses and the event key are the sn_keccak of their names as Cairo 1 computes them.
+// Written for Cairo 2.3.1 (the cairo-lang crates of Cargo.toml), cairo_program/compile.sh
+// compiles it to Sierra and CASM.
+
+#[derive(Copy, Drop, Serde, starknet::Store)]
+struct Point {
+    x: felt252,
+    y: felt252,
+}
+
+#[derive(Copy, Drop, Serde)]
+struct Claim {
+    user_key: Point,
+    sequencer_key: Point,
+    user_nonce: Point,
+    nonce: Point,
+    response: felt252,
+    user_response: felt252,
+    message: felt252,
+    index: felt252,
+}
+
+// levels of a batch tree, a batch holds the indices [b * 2^32, (b + 1) * 2^32)
+const BATCH_DEPTH: u32 = 32;
+
+#[starknet::contract]
+mod dispute_contract {
+    use core::ec::{EcPointTrait, EcStateTrait, NonZeroEcPoint};
+    use core::ec::stark_curve::{GEN_X, GEN_Y};
+    use core::pedersen::pedersen;
+    use starknet::{ContractAddress, contract_address_const, get_caller_address};
+    use super::{BATCH_DEPTH, Claim, Point};
+
+    #[storage]
+    struct Storage {
+        sequencer: ContractAddress,
+        sequencer_key: Point,
+        // root of each batch, 0 : not posted
+        roots: LegacyMap<felt252, felt252>,
+        slashed: bool,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    enum Event {
+        Fraud: Fraud,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    struct Fraud {
+        sequencer: ContractAddress,
+        message: felt252,
+        index: felt252,
+        leaf: felt252,
+    }
+
+    // The caller becomes the sequencer, once
+    #[external(v0)]
+    fn register(ref self: ContractState, key: Point) {
+        assert(self.sequencer.read() == contract_address_const::<0>(), 'already registered');
+        self.sequencer.write(get_caller_address());
+        self.sequencer_key.write(key);
+    }
+
+    // The sequencer posts the root of batch 'batch', once
+    #[external(v0)]
+    fn post_root(ref self: ContractState, batch: felt252, root: felt252) {
+        assert(get_caller_address() == self.sequencer.read(), 'not the sequencer');
+        assert(self.roots.read(batch) == 0, 'root already posted');
+        self.roots.write(batch, root);
+    }
+
+    // Returns 1 and slashes the sequencer if the batch it posted for claim.index doesn't hold
+    // claim.message there : 'leaf' is what it holds, 'siblings' its Merkle path. Fails if the
+    // claim is not signed with the registered key or the opening doesn't match the posted root.
+    #[external(v0)]
+    fn dispute(ref self: ContractState, claim: Claim, leaf: felt252, siblings: Span<felt252>) -> felt252 {
+        let key = self.sequencer_key.read();
+        assert(claim.sequencer_key.x == key.x, 'unknown sequencer key');
+        assert(claim.sequencer_key.y == key.y, 'unknown sequencer key');
+
+        let index: u256 = claim.index.into();
+        let batch_size: u256 = 0x100000000;
+        let batch: felt252 = (index / batch_size).try_into().unwrap();
+        let root = self.roots.read(batch);
+        if !verify_claim(claim, batch, root, leaf, siblings) {
+            return 0;
+        }
+
+        self.slashed.write(true);
+        let sequencer = self.sequencer.read();
+        self.emit(Fraud { sequencer, message: claim.message, index: claim.index, leaf });
+        1
+    }
+
+    #[external(v0)]
+    fn slashed(self: @ContractState) -> felt252 {
+        if self.slashed.read() {
+            1
+        } else {
+            0
+        }
+    }
+
+    // StarkNet's compute_hash_on_elements : H(H(...H(H(0, elements[0]), elements[1])...), n)
+    fn hash_on_elements(elements: Span<felt252>) -> felt252 {
+        let mut acc = 0;
+        let mut i = 0;
+        loop {
+            if i == elements.len() {
+                break;
+            }
+            acc = pedersen(acc, *elements[i]);
+            i += 1;
+        };
+        pedersen(acc, elements.len().into())
+    }
+
+    // Root of the batch tree whose leaf at 'index' is 'leaf' and the batch of 'index', merkle_root
+    // of dispute.cairo
+    fn merkle_root(leaf: felt252, index: u256, siblings: Span<felt252>) -> (felt252, u256) {
+        assert(siblings.len() == BATCH_DEPTH, 'wrong opening depth');
+        let mut node = leaf;
+        let mut position = index;
+        let mut i = 0;
+        loop {
+            if i == siblings.len() {
+                break;
+            }
+            if position % 2 == 0 {
+                node = pedersen(node, *siblings[i]);
+            } else {
+                node = pedersen(*siblings[i], node);
+            }
+            position = position / 2;
+            i += 1;
+        };
+        (node, position)
+    }
+
+    fn point(p: Point) -> NonZeroEcPoint {
+        EcPointTrait::new_nz(p.x, p.y).expect('invalid point')
+    }
+
+    // Returns true if the sequencer co-signed claim.message at claim.index but batch 'batch', of
+    // this root, holds another leaf there, see dispute.cairo
+    fn verify_claim(claim: Claim, batch: felt252, root: felt252, leaf: felt252, siblings: Span<felt252>) -> bool {
+        let (p_u, p_s) = (claim.user_key, claim.sequencer_key);
+        let l = hash_on_elements(array![p_u.x, p_u.y, p_s.x, p_s.y].span());
+        let w_u = hash_on_elements(array![l, p_u.x, p_u.y].span());
+        let w_s = hash_on_elements(array![l, p_s.x, p_s.y].span());
+
+        // X = w_u * P_u + w_s * P_s
+        let mut state = EcStateTrait::init();
+        state.add_mul(w_u, point(p_u));
+        state.add_mul(w_s, point(p_s));
+        let (x_x, x_y) = state.finalize_nz().expect('invalid point').coordinates();
+
+        let r = claim.nonce;
+        let e = hash_on_elements(array![r.x, r.y, x_x, x_y, claim.message, claim.index].span());
+
+        // Y = X - w_u * P_u = w_s * P_s
+        let mut state = EcStateTrait::init();
+        state.add_mul(w_s, point(p_s));
+        let (y_x, y_y) = state.finalize_nz().expect('invalid point').coordinates();
+
+        // R_u + s * G == (R + e * Y) + s_u * G
+        let g = point(Point { x: GEN_X, y: GEN_Y });
+        let mut lhs = EcStateTrait::init();
+        lhs.add(point(claim.user_nonce));
+        lhs.add_mul(claim.response, g);
+        let (l_x, l_y) = lhs.finalize_nz().expect('invalid point').coordinates();
+        let mut rhs = EcStateTrait::init();
+        rhs.add(point(r));
+        rhs.add_mul(e, point(Point { x: y_x, y: y_y }));
+        rhs.add_mul(claim.user_response, g);
+        let (r_x, r_y) = rhs.finalize_nz().expect('invalid point').coordinates();
+        assert(l_x == r_x, 'invalid signature');
+        assert(l_y == r_y, 'invalid signature');
+
+        let (opened, opened_batch) = merkle_root(leaf, claim.index.into(), siblings);
+        assert(opened_batch == batch.into(), 'wrong batch');
+        assert(opened == root, 'invalid opening');
+        leaf != claim.message
+    }
+}
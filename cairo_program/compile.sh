@@ -0,0 +1,26 @@
+#!/bin/sh
+# Builds the programs the registry reads from cairo_program/build/ (see src/cairo/registry.rs).
+#   CAIRO1_BIN=.../cairo/bin cairo_program/compile.sh && cargo test -- --ignored
+# Needs cairo-lang 0.10.3 on the PATH : pip install cairo-lang==0.10.3
+set -e
+cd "$(dirname "$0")/.."
+mkdir -p cairo_program/build
+
+cairo-compile cairo_program/dispute.cairo --output cairo_program/build/dispute.json
+cairo-compile cairo_program/sequencer_hints.cairo --output cairo_program/build/sequencer_hints.json
+cairo-compile --proof_mode cairo_program/abs_value_array.cairo --output cairo_program/build/abs_value_array_proof.json
+
+# the executor runs the program of the contract class, its functions are called by name
+starknet-compile cairo_program/dispute_contract.cairo --cairo_path cairo_program \
+    --output cairo_program/build/dispute_contract_class.json
+python3 -c 'import json, sys; json.dump(json.load(open(sys.argv[1]))["program"], open(sys.argv[2], "w"))' \
+    cairo_program/build/dispute_contract_class.json cairo_program/build/dispute_contract.json
+
+# Cairo 1 : starknet-compile and starknet-sierra-compile 2.3.1, the version of the cairo-lang
+# crates in Cargo.toml (https://github.com/starkware-libs/cairo/releases/tag/v2.3.1). cairo-lang
+# has its own starknet-compile, so they are taken from CAIRO1_BIN : the bin/ of the release
+CAIRO1_BIN=${CAIRO1_BIN:?set CAIRO1_BIN to the bin directory of Cairo 2.3.1}
+"$CAIRO1_BIN/starknet-compile" --single-file cairo_program/cairo1/dispute_contract.cairo \
+    cairo_program/build/dispute_contract.contract_class.json
+"$CAIRO1_BIN/starknet-sierra-compile" cairo_program/build/dispute_contract.contract_class.json \
+    cairo_program/build/dispute_contract.compiled_contract_class.json
@@ -0,0 +1,140 @@
+%builtins pedersen range_check ec_op
+
+// Dispute verification of the sequencer's co-signature (see src/main.rs, "Smart Contract").
+// On StarkNet the MuSig transcript lives on the STARK curve and hashes with Pedersen :
+//   l = H(P_u, P_s), w_u = H(l, P_u), w_s = H(l, P_s), X = w_u * P_u + w_s * P_s
+//   e = H(R, X, m, i), (s - s_u) * G == (R - R_u) + e * (X - w_u * P_u)
+// where H is StarkNet's hash on elements. A claim whose signature does not verify is rejected.
+// What the sequencer included is read from the root it posted for the batch of i : a Merkle tree
+// of depth BATCH_DEPTH over the messages (node = pedersen(left, right), empty slots are 0). The
+// user opens the leaf at i, and the sequencer lied if it is not m. No other leaf opens at i, so
+// an honest co-signature can't be turned into a fraud.
+// This transcript is not the one of src/schnorr.rs : a BN254/Poseidon receipt can't be disputed
+// here, the sequencer has to co-sign on the STARK curve as well (verifying BN254 in Cairo is the
+// blocked user-040).
+
+from starkware.cairo.common.alloc import alloc
+from starkware.cairo.common.cairo_builtins import EcOpBuiltin, HashBuiltin
+from starkware.cairo.common.ec_point import EcPoint
+from starkware.cairo.common.math import unsigned_div_rem
+
+const GEN_X = 0x1ef15c18599971b7beced415a40f0c7deacfd9b0d1819e03d723d8bc943cfca;
+const GEN_Y = 0x5668060aa49730b7be4801df46ec62de53ecd11abe43a32873000c36e8dc1f;
+
+// a batch holds the messages of indices [b * BATCH_SIZE, (b + 1) * BATCH_SIZE)
+const BATCH_DEPTH = 32;
+const BATCH_SIZE = 2 ** 32;
+
+struct Claim {
+    user_key: EcPoint,
+    sequencer_key: EcPoint,
+    user_nonce: EcPoint,
+    nonce: EcPoint,
+    response: felt,
+    user_response: felt,
+    message: felt,
+    index: felt,
+}
+
+// H(...H(H(acc, elements[0]), elements[1])..., elements[n - 1])
+func hash_chain{pedersen_ptr: HashBuiltin*}(acc: felt, elements: felt*, n: felt) -> (res: felt) {
+    if (n == 0) {
+        return (res=acc);
+    }
+    assert pedersen_ptr.x = acc;
+    assert pedersen_ptr.y = elements[0];
+    let acc = pedersen_ptr.result;
+    let pedersen_ptr = pedersen_ptr + HashBuiltin.SIZE;
+    return hash_chain(acc, elements + 1, n - 1);
+}
+
+// StarkNet's compute_hash_on_elements : H(hash_chain(0, elements, n), n)
+func hash_on_elements{pedersen_ptr: HashBuiltin*}(elements: felt*, n: felt) -> (res: felt) {
+    let (acc) = hash_chain(0, elements, n);
+    assert pedersen_ptr.x = acc;
+    assert pedersen_ptr.y = n;
+    let res = pedersen_ptr.result;
+    let pedersen_ptr = pedersen_ptr + HashBuiltin.SIZE;
+    return (res=res);
+}
+
+// Root of the tree of depth 'depth' whose leaf at 'index' is 'leaf', siblings from the bottom up.
+// Also returns what is left of the index above the tree (the batch number for BATCH_DEPTH).
+func merkle_root{pedersen_ptr: HashBuiltin*, range_check_ptr}(
+    leaf: felt, index: felt, siblings: felt*, depth: felt
+) -> (root: felt, batch: felt) {
+    if (depth == 0) {
+        return (root=leaf, batch=index);
+    }
+    let (parent, bit) = unsigned_div_rem(index, 2);
+    // (leaf, sibling) for a left child, (sibling, leaf) for a right one
+    assert pedersen_ptr.x = leaf + bit * (siblings[0] - leaf);
+    assert pedersen_ptr.y = siblings[0] + bit * (leaf - siblings[0]);
+    let node = pedersen_ptr.result;
+    let pedersen_ptr = pedersen_ptr + HashBuiltin.SIZE;
+    return merkle_root(node, parent, siblings + 1, depth - 1);
+}
+
+// Returns 1 if the sequencer co-signed claim.message at claim.index but the batch it posted holds
+// another leaf there. 'root' is the root the sequencer posted for batch 'batch', 'leaf' and its
+// BATCH_DEPTH 'siblings' open it at claim.index. Fails if the opening doesn't match the root or
+// claim.index is not in that batch (the root of a batch says nothing about the others).
+func verify_claim{pedersen_ptr: HashBuiltin*, range_check_ptr, ec_op_ptr: EcOpBuiltin*}(
+    claim: Claim*, batch: felt, root: felt, leaf: felt, siblings: felt*
+) -> (fraud: felt) {
+    alloc_locals;
+    let (local l) = hash_on_elements(cast(claim, felt*), 4);
+
+    let (local buffer: felt*) = alloc();
+    assert buffer[0] = l;
+    assert buffer[1] = claim.user_key.x;
+    assert buffer[2] = claim.user_key.y;
+    assert buffer[3] = l;
+    assert buffer[4] = claim.sequencer_key.x;
+    assert buffer[5] = claim.sequencer_key.y;
+    let (local w_u) = hash_on_elements(buffer, 3);
+    let (local w_s) = hash_on_elements(buffer + 3, 3);
+
+    // X = (P_s + w_u * P_u) + (w_s - 1) * P_s
+    assert ec_op_ptr[0].p = claim.sequencer_key;
+    assert ec_op_ptr[0].q = claim.user_key;
+    assert ec_op_ptr[0].m = w_u;
+    assert ec_op_ptr[1].p = ec_op_ptr[0].r;
+    assert ec_op_ptr[1].q = claim.sequencer_key;
+    assert ec_op_ptr[1].m = w_s - 1;
+    let x = ec_op_ptr[1].r;
+
+    assert buffer[6] = claim.nonce.x;
+    assert buffer[7] = claim.nonce.y;
+    assert buffer[8] = x.x;
+    assert buffer[9] = x.y;
+    assert buffer[10] = claim.message;
+    assert buffer[11] = claim.index;
+    let (local e) = hash_on_elements(buffer + 6, 6);
+
+    // Y = X - w_u * P_u
+    assert ec_op_ptr[2].p = x;
+    assert ec_op_ptr[2].q = EcPoint(claim.user_key.x, -claim.user_key.y);
+    assert ec_op_ptr[2].m = w_u;
+
+    // R_u + s * G == (R + e * Y) + s_u * G
+    assert ec_op_ptr[3].p = claim.user_nonce;
+    assert ec_op_ptr[3].q = EcPoint(GEN_X, GEN_Y);
+    assert ec_op_ptr[3].m = claim.response;
+    assert ec_op_ptr[4].p = claim.nonce;
+    assert ec_op_ptr[4].q = ec_op_ptr[2].r;
+    assert ec_op_ptr[4].m = e;
+    assert ec_op_ptr[5].p = ec_op_ptr[4].r;
+    assert ec_op_ptr[5].q = EcPoint(GEN_X, GEN_Y);
+    assert ec_op_ptr[5].m = claim.user_response;
+    assert ec_op_ptr[3].r = ec_op_ptr[5].r;
+    let ec_op_ptr = ec_op_ptr + 6 * EcOpBuiltin.SIZE;
+
+    let (opened, opened_batch) = merkle_root(leaf, claim.index, siblings, BATCH_DEPTH);
+    assert opened_batch = batch;
+    assert opened = root;
+    if (leaf == claim.message) {
+        return (fraud=0);
+    }
+    return (fraud=1);
+}
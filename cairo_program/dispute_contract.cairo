@@ -0,0 +1,102 @@
+%lang starknet
+
+// The dispute contract of main.rs ("Smart Contract") : the sequencer registers its key and posts
+// the root of each batch of messages it sequenced (see dispute.cairo), a user holding a
+// co-signature for a message the posted batch doesn't hold at its index gets it slashed.
+// Storage addresses and event keys are short strings, the root of batch b lives at H(ROOTS, b).
+
+from starkware.cairo.common.alloc import alloc
+from starkware.cairo.common.cairo_builtins import EcOpBuiltin, HashBuiltin
+from starkware.cairo.common.ec_point import EcPoint
+from starkware.cairo.common.math import unsigned_div_rem
+from starkware.cairo.common.registers import get_fp_and_pc
+from starkware.starknet.common.syscalls import (
+    emit_event,
+    get_caller_address,
+    storage_read,
+    storage_write,
+)
+
+from dispute import BATCH_DEPTH, BATCH_SIZE, Claim, verify_claim
+
+const SEQUENCER = 'sequencer';
+const SEQUENCER_KEY = 'sequencer_key';
+const ROOTS = 'roots';
+const SLASHED = 'slashed';
+const FRAUD = 'Fraud';
+
+// The caller becomes the sequencer, once
+@external
+func register{syscall_ptr: felt*}(key: EcPoint) {
+    alloc_locals;
+    let (local sequencer) = storage_read(address=SEQUENCER);
+    assert sequencer = 0;
+    let (local caller) = get_caller_address();
+    storage_write(address=SEQUENCER, value=caller);
+    storage_write(address=SEQUENCER_KEY, value=key.x);
+    storage_write(address=SEQUENCER_KEY + 1, value=key.y);
+    return ();
+}
+
+// The sequencer posts the root of batch 'batch', once
+@external
+func post_root{syscall_ptr: felt*, pedersen_ptr: HashBuiltin*}(batch: felt, root: felt) {
+    alloc_locals;
+    let (local sequencer) = storage_read(address=SEQUENCER);
+    let (local caller) = get_caller_address();
+    assert caller = sequencer;
+    assert pedersen_ptr.x = ROOTS;
+    assert pedersen_ptr.y = batch;
+    local address = pedersen_ptr.result;
+    let pedersen_ptr = pedersen_ptr + HashBuiltin.SIZE;
+    let (local posted) = storage_read(address=address);
+    assert posted = 0;
+    storage_write(address=address, value=root);
+    return ();
+}
+
+// Returns 1 and slashes the sequencer if the batch it posted for claim.index doesn't hold
+// claim.message there : 'leaf' is what it holds, 'siblings' its Merkle path. Fails if the claim is
+// not signed with the registered key or the opening doesn't match the posted root (a batch that
+// was not posted has root 0, which no opening matches).
+@external
+func dispute{syscall_ptr: felt*, pedersen_ptr: HashBuiltin*, range_check_ptr, ec_op_ptr: EcOpBuiltin*}(
+    claim: Claim, leaf: felt, siblings_len: felt, siblings: felt*
+) -> (fraud: felt) {
+    alloc_locals;
+    let (__fp__, _) = get_fp_and_pc();
+    assert siblings_len = BATCH_DEPTH;
+    let (local key_x) = storage_read(address=SEQUENCER_KEY);
+    assert claim.sequencer_key.x = key_x;
+    let (local key_y) = storage_read(address=SEQUENCER_KEY + 1);
+    assert claim.sequencer_key.y = key_y;
+
+    let (local batch, _) = unsigned_div_rem(claim.index, BATCH_SIZE);
+    assert pedersen_ptr.x = ROOTS;
+    assert pedersen_ptr.y = batch;
+    local address = pedersen_ptr.result;
+    let pedersen_ptr = pedersen_ptr + HashBuiltin.SIZE;
+    let (local root) = storage_read(address=address);
+    let (local fraud) = verify_claim(&claim, batch, root, leaf, siblings);
+    if (fraud == 0) {
+        return (fraud=0);
+    }
+
+    storage_write(address=SLASHED, value=1);
+    let (local sequencer) = storage_read(address=SEQUENCER);
+    let (local keys: felt*) = alloc();
+    assert keys[0] = FRAUD;
+    let (local data: felt*) = alloc();
+    assert data[0] = sequencer;
+    assert data[1] = claim.message;
+    assert data[2] = claim.index;
+    assert data[3] = leaf;
+    emit_event(keys_len=1, keys=keys, data_len=4, data=data);
+    return (fraud=1);
+}
+
+@view
+func slashed{syscall_ptr: felt*}() -> (res: felt) {
+    let (res) = storage_read(address=SLASHED);
+    return (res=res);
+}
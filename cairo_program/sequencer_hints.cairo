@@ -0,0 +1,42 @@
+// Calls into the sequencer's native cryptography through the hints of cairo::hints.
+// BN254 values are Uint256 (see cairo::convert) : the hints compute, Cairo only moves the results.
+// Nothing here is proven, a verifier must still check the hinted values.
+
+from starkware.cairo.common.uint256 import Uint256
+
+struct G1Point {
+    x: Uint256,
+    y: Uint256,
+}
+
+// Poseidon over BN254 Fq of inputs[0..n_inputs]
+func bn254_poseidon(inputs: Uint256*, n_inputs: felt) -> (res: Uint256) {
+    alloc_locals;
+    local res: Uint256;
+    %{ bn254_poseidon(ids.inputs, ids.n_inputs, ids.res) %}
+    return (res=res);
+}
+
+// MuSig key coefficients w_u = H(L, P_u) and w_s = H(L, P_s), L = H(P_u, P_s)
+func musig_coefficients(user_key: G1Point*, sequencer_key: G1Point*) -> (w_u: Uint256, w_s: Uint256) {
+    alloc_locals;
+    local w_u: Uint256;
+    local w_s: Uint256;
+    %{ musig_coefficients(ids.user_key, ids.sequencer_key, ids.w_u, ids.w_s) %}
+    return (w_u=w_u, w_s=w_s);
+}
+
+func bn254_ec_add(p: G1Point*, q: G1Point*) -> (res: G1Point) {
+    alloc_locals;
+    local res: G1Point;
+    %{ bn254_ec_add(ids.p, ids.q, ids.res) %}
+    return (res=res);
+}
+
+// k is a BN254 Fr scalar
+func bn254_ec_mul(p: G1Point*, k: Uint256) -> (res: G1Point) {
+    alloc_locals;
+    local res: G1Point;
+    %{ bn254_ec_mul(ids.p, ids.k, ids.res) %}
+    return (res=res);
+}
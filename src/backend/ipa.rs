@@ -0,0 +1,113 @@
+use halo2_proofs::pasta::{pallas, vesta};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, ProvingKey, SingleVerifier, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
+use rand::rngs::OsRng;
+
+use super::{BackendError, CircuitShape, ProofSystem, Provable, MAX_K};
+
+// IPA over the Pasta cycle : circuits over pallas::Base, commitments in vesta
+pub struct IpaPasta;
+
+impl ProofSystem for IpaPasta {
+    const NAME: &'static str = "ipa-pasta";
+
+    type Scalar = pallas::Base;
+    type Params = Params<vesta::Affine>;
+    type ProvingKey = ProvingKey<vesta::Affine>;
+    type VerifyingKey = VerifyingKey<vesta::Affine>;
+
+    fn setup(k: u32) -> Self::Params {
+        Params::new(k)
+    }
+
+    fn vk(pk: &Self::ProvingKey) -> &Self::VerifyingKey {
+        pk.get_vk()
+    }
+
+    fn verify(params: &Self::Params, vk: &Self::VerifyingKey, instances: &[Vec<Self::Scalar>], proof: &[u8]) -> bool {
+        let instances = instances.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let strategy = SingleVerifier::new(params);
+        let mut transcript = Blake2bRead::<_, vesta::Affine, Challenge255<_>>::init(proof);
+        verify_proof(params, vk, strategy, &[&instances[..]], &mut transcript).is_ok()
+    }
+}
+
+impl<C: Circuit<pallas::Base>> Provable<IpaPasta> for C {
+    fn keygen(&self, params: &Params<vesta::Affine>) -> Result<ProvingKey<vesta::Affine>, BackendError> {
+        let vk = keygen_vk(params, self).map_err(BackendError::Ipa)?;
+        keygen_pk(params, vk, self).map_err(BackendError::Ipa)
+    }
+
+    fn prove(
+        &self,
+        params: &Params<vesta::Affine>,
+        pk: &ProvingKey<vesta::Affine>,
+        instances: &[Vec<pallas::Base>],
+    ) -> Result<Vec<u8>, BackendError> {
+        let instances = instances.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+        create_proof(params, pk, std::slice::from_ref(self), &[&instances[..]], OsRng, &mut transcript)
+            .map_err(BackendError::Ipa)?;
+        Ok(transcript.finalize())
+    }
+
+    fn shape() -> CircuitShape {
+        let mut cs = ConstraintSystem::<pallas::Base>::default();
+        C::configure(&mut cs);
+        CircuitShape {
+            advice_columns: cs.num_advice_columns(),
+            fixed_columns: cs.num_fixed_columns(),
+            instance_columns: cs.num_instance_columns(),
+            selectors: cs.num_selectors(),
+            gates: cs.gates().len(),
+            lookups: cs.lookups().len(),
+            degree: cs.degree(),
+        }
+    }
+
+    fn min_k(&self, instances: &[Vec<pallas::Base>]) -> Option<u32> {
+        (1..=MAX_K).find(|k| {
+            MockProver::run(*k, self, instances.to_vec())
+                .map(|prover| prover.verify().is_ok())
+                .unwrap_or(false)
+        })
+    }
+}
+
+
+#[test]
+pub fn test_ipa_merkle_proof() {
+    use crate::circuits::merkle::{k, MerkleCircuit};
+    use super::merkle_witness;
+
+    const DEPTH: usize = 8;
+    let (leaf, path, root) = merkle_witness::<pallas::Base, DEPTH>();
+    let circuit = MerkleCircuit::<_, DEPTH>::new(leaf, &path);
+    let instances = vec![MerkleCircuit::<_, DEPTH>::instance(root, path.index)];
+
+    let params = IpaPasta::setup(k(DEPTH));
+    let pk = IpaPasta::keygen(&params, &MerkleCircuit::<_, DEPTH>::default()).unwrap();
+    let proof = IpaPasta::prove(&params, &pk, &circuit, &instances).unwrap();
+    assert!(IpaPasta::verify(&params, IpaPasta::vk(&pk), &instances, &proof));
+
+    let wrong = vec![MerkleCircuit::<_, DEPTH>::instance(root, path.index + 1)];
+    assert!(!IpaPasta::verify(&params, IpaPasta::vk(&pk), &wrong, &proof));
+}
+
+// cargo test --release bench_ipa_merkle -- --ignored --nocapture
+#[test]
+#[ignore]
+pub fn bench_ipa_merkle() {
+    use crate::circuits::merkle::{k, MerkleCircuit};
+    use super::{bench, merkle_witness};
+
+    let (leaf, path, root) = merkle_witness::<pallas::Base, 20>();
+    let instances = vec![MerkleCircuit::<_, 20>::instance(root, path.index)];
+    bench::<IpaPasta, _>("merkle depth 20", k(20), &MerkleCircuit::default(), &MerkleCircuit::new(leaf, &path), &instances);
+
+    let (leaf, path, root) = merkle_witness::<pallas::Base, 32>();
+    let instances = vec![MerkleCircuit::<_, 32>::instance(root, path.index)];
+    bench::<IpaPasta, _>("merkle depth 32", k(32), &MerkleCircuit::default(), &MerkleCircuit::new(leaf, &path), &instances);
+}
@@ -0,0 +1,154 @@
+use halo2_pse::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_pse::dev::MockProver;
+use halo2_pse::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, ProvingKey, VerifyingKey};
+use halo2_pse::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_pse::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_pse::poly::kzg::strategy::SingleStrategy;
+use halo2_pse::transcript::{Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer};
+use rand::rngs::OsRng;
+
+use super::{BackendError, CircuitShape, ProofSystem, Provable, MAX_K};
+
+// KZG on BN254 with the SHPLONK multiopening : circuits over bn256::Fr
+pub struct KzgBn254;
+
+// The backend independent circuits, built against halo2_pse (schnorr and equivocation are
+// IPA-only, see circuits/mod.rs)
+pub mod circuits {
+    pub mod poseidon {
+        use halo2_pse as halo2;
+        include!("../circuits/poseidon.rs");
+
+        #[test]
+        pub fn test_poseidon_chip_bn256_fr() {
+            differential::<halo2_pse::halo2curves::bn256::Fr>();
+        }
+
+        #[test]
+        pub fn test_poseidon_chip_bn256_fq() {
+            differential::<halo2_pse::halo2curves::bn256::Fq>();
+        }
+    }
+    pub mod merkle {
+        use halo2_pse as halo2;
+        include!("../circuits/merkle.rs");
+
+        #[test]
+        pub fn test_merkle_circuit_bn256_fr() {
+            check_merkle_circuit::<halo2_pse::halo2curves::bn256::Fr>();
+        }
+    }
+}
+
+impl ProofSystem for KzgBn254 {
+    const NAME: &'static str = "kzg-bn254";
+
+    type Scalar = Fr;
+    type Params = ParamsKZG<Bn256>;
+    type ProvingKey = ProvingKey<G1Affine>;
+    type VerifyingKey = VerifyingKey<G1Affine>;
+
+    // [WARNING] the toxic waste is known to this process, production parameters come from a
+    // ceremony (see keys::KeyCache to load them from disk)
+    fn setup(k: u32) -> Self::Params {
+        ParamsKZG::<Bn256>::setup(k, OsRng)
+    }
+
+    fn vk(pk: &Self::ProvingKey) -> &Self::VerifyingKey {
+        pk.get_vk()
+    }
+
+    fn verify(params: &Self::Params, vk: &Self::VerifyingKey, instances: &[Vec<Self::Scalar>], proof: &[u8]) -> bool {
+        let instances = instances.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let strategy = SingleStrategy::new(params);
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+        verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            params,
+            vk,
+            strategy,
+            &[&instances[..]],
+            &mut transcript,
+        )
+        .is_ok()
+    }
+}
+
+impl<C: Circuit<Fr>> Provable<KzgBn254> for C {
+    fn keygen(&self, params: &ParamsKZG<Bn256>) -> Result<ProvingKey<G1Affine>, BackendError> {
+        let vk = keygen_vk(params, self).map_err(BackendError::Kzg)?;
+        keygen_pk(params, vk, self).map_err(BackendError::Kzg)
+    }
+
+    fn prove(&self, params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>, instances: &[Vec<Fr>]) -> Result<Vec<u8>, BackendError> {
+        let instances = instances.iter().map(|i| &i[..]).collect::<Vec<_>>();
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+            params,
+            pk,
+            std::slice::from_ref(self),
+            &[&instances[..]],
+            OsRng,
+            &mut transcript,
+        )
+        .map_err(BackendError::Kzg)?;
+        Ok(transcript.finalize())
+    }
+
+    fn shape() -> CircuitShape {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        C::configure(&mut cs);
+        CircuitShape {
+            advice_columns: cs.num_advice_columns(),
+            fixed_columns: cs.num_fixed_columns(),
+            instance_columns: cs.num_instance_columns(),
+            selectors: cs.num_selectors(),
+            gates: cs.gates().len(),
+            lookups: cs.lookups().len(),
+            degree: cs.degree(),
+        }
+    }
+
+    fn min_k(&self, instances: &[Vec<Fr>]) -> Option<u32> {
+        (1..=MAX_K).find(|k| {
+            MockProver::run(*k, self, instances.to_vec())
+                .map(|prover| prover.verify().is_ok())
+                .unwrap_or(false)
+        })
+    }
+}
+
+
+#[test]
+pub fn test_kzg_merkle_proof() {
+    use circuits::merkle::{k, MerkleCircuit};
+    use super::merkle_witness;
+
+    const DEPTH: usize = 8;
+    let (leaf, path, root) = merkle_witness::<Fr, DEPTH>();
+    let circuit = MerkleCircuit::<_, DEPTH>::new(leaf, &path);
+    let instances = vec![MerkleCircuit::<_, DEPTH>::instance(root, path.index)];
+
+    let params = KzgBn254::setup(k(DEPTH));
+    let pk = KzgBn254::keygen(&params, &MerkleCircuit::<_, DEPTH>::default()).unwrap();
+    let proof = KzgBn254::prove(&params, &pk, &circuit, &instances).unwrap();
+    assert!(KzgBn254::verify(&params, KzgBn254::vk(&pk), &instances, &proof));
+
+    let wrong = vec![MerkleCircuit::<_, DEPTH>::instance(root, path.index + 1)];
+    assert!(!KzgBn254::verify(&params, KzgBn254::vk(&pk), &wrong, &proof));
+}
+
+// cargo test --release bench_kzg_merkle -- --ignored --nocapture
+#[test]
+#[ignore]
+pub fn bench_kzg_merkle() {
+    use circuits::merkle::{k, MerkleCircuit};
+    use super::{bench, merkle_witness};
+
+    let (leaf, path, root) = merkle_witness::<Fr, 20>();
+    let instances = vec![MerkleCircuit::<_, 20>::instance(root, path.index)];
+    bench::<KzgBn254, _>("merkle depth 20", k(20), &MerkleCircuit::default(), &MerkleCircuit::new(leaf, &path), &instances);
+
+    let (leaf, path, root) = merkle_witness::<Fr, 32>();
+    let instances = vec![MerkleCircuit::<_, 32>::instance(root, path.index)];
+    bench::<KzgBn254, _>("merkle depth 32", k(32), &MerkleCircuit::default(), &MerkleCircuit::new(leaf, &path), &instances);
+}
@@ -0,0 +1,109 @@
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+use serde::Serialize;
+
+// Proving backends, one per halo2 flavour, selected by cargo feature :
+//   ipa-pasta : zcash halo2_proofs 0.3, IPA commitments over the Pasta cycle (no trusted setup)
+//   kzg-bn254 : PSE halo2 (halo2_pse), KZG commitments on BN254 (cheap EVM verification)
+// A circuit written against the core API (circuits::poseidon, circuits::merkle) is built once
+// per backend and proven through the same ProofSystem interface.
+
+#[cfg(feature = "ipa-pasta")]
+pub mod ipa;
+#[cfg(feature = "kzg-bn254")]
+pub mod kzg;
+
+#[derive(Debug)]
+pub enum BackendError {
+    #[cfg(feature = "ipa-pasta")]
+    Ipa(halo2_proofs::plonk::Error),
+    #[cfg(feature = "kzg-bn254")]
+    Kzg(halo2_pse::plonk::Error),
+}
+
+pub trait ProofSystem: Sized {
+    const NAME: &'static str;
+
+    type Scalar: FromUniformBytes<64> + Ord;
+    type Params;
+    type ProvingKey;
+    type VerifyingKey;
+
+    fn setup(k: u32) -> Self::Params;
+
+    fn vk(pk: &Self::ProvingKey) -> &Self::VerifyingKey;
+
+    fn keygen<C: Provable<Self>>(params: &Self::Params, circuit: &C) -> Result<Self::ProvingKey, BackendError> {
+        circuit.keygen(params)
+    }
+
+    // 'instances' holds one vector per instance column
+    fn prove<C: Provable<Self>>(
+        params: &Self::Params,
+        pk: &Self::ProvingKey,
+        circuit: &C,
+        instances: &[Vec<Self::Scalar>],
+    ) -> Result<Vec<u8>, BackendError> {
+        circuit.prove(params, pk, instances)
+    }
+
+    fn verify(params: &Self::Params, vk: &Self::VerifyingKey, instances: &[Vec<Self::Scalar>], proof: &[u8]) -> bool;
+}
+
+// Implemented for every circuit of the backend's halo2 flavour (blanket impls in ipa / kzg)
+pub trait Provable<P: ProofSystem> {
+    fn keygen(&self, params: &P::Params) -> Result<P::ProvingKey, BackendError>;
+
+    fn prove(&self, params: &P::Params, pk: &P::ProvingKey, instances: &[Vec<P::Scalar>]) -> Result<Vec<u8>, BackendError>;
+
+    fn shape() -> CircuitShape;
+
+    // smallest k for which the circuit fits and the MockProver accepts the witness, up to MAX_K
+    fn min_k(&self, instances: &[Vec<P::Scalar>]) -> Option<u32>;
+}
+
+pub const MAX_K: u32 = 20;
+
+// Layout of a circuit, read from its constraint system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CircuitShape {
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub gates: usize,
+    pub lookups: usize,
+    pub degree: usize,
+}
+
+
+// keygen (on the circuit without witnesses), prove and verify, with timings
+#[cfg(test)]
+pub fn bench<P: ProofSystem, C: Provable<P>>(label: &str, k: u32, empty: &C, circuit: &C, instances: &[Vec<P::Scalar>]) {
+    use std::time::Instant;
+
+    let params = P::setup(k);
+    let now = Instant::now();
+    let pk = P::keygen(&params, empty).unwrap();
+    println!("[{}] {} (k = {}) keygen took: {:?}", P::NAME, label, k, now.elapsed());
+
+    let now = Instant::now();
+    let proof = P::prove(&params, &pk, circuit, instances).unwrap();
+    println!("[{}] {} proving took: {:?}, proof size: {} bytes", P::NAME, label, now.elapsed(), proof.len());
+
+    let now = Instant::now();
+    assert!(P::verify(&params, P::vk(&pk), instances, &proof));
+    println!("[{}] {} verifying took: {:?}", P::NAME, label, now.elapsed());
+}
+
+// (leaf, path, root) for the last leaf of a small tree of depth DEPTH
+pub fn merkle_witness<F: FromUniformBytes<64> + Ord, const DEPTH: usize>() -> (F, crate::merkle::InclusionProof<F, DEPTH>, F) {
+    use rand::rngs::OsRng;
+    use crate::merkle::{capacity, MerkleTree};
+
+    // only the path matters, a handful of leaves is enough (at most what the tree holds)
+    let mut tree = MerkleTree::<F, DEPTH>::new();
+    let leaves = (0..capacity(DEPTH).min(100)).map(|_| F::random(OsRng)).collect::<Vec<F>>();
+    leaves.iter().for_each(|leaf| { tree.append(*leaf); });
+    let index = leaves.len() - 1;
+    (leaves[index], tree.prove(index as u64).unwrap(), tree.root())
+}
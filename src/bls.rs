@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use halo2_pse::halo2curves::bn256::{Bn256, Fq as Base, Fr as Scalar, G1Affine, G2Affine, G2Prepared, G1, G2};
+use halo2_pse::halo2curves::ff::{Field, PrimeField};
+use halo2_pse::halo2curves::group::{Curve, Group, GroupEncoding};
+use halo2_pse::halo2curves::pairing::{MillerLoopResult, MultiMillerLoop};
+use halo2_pse::halo2curves::CurveAffine;
+use rand::{CryptoRng, RngCore};
+
+use crate::halo2::poseidon_hash;
+
+// BLS signatures on BN254 : signatures in G1, public keys in G2.
+// sign : σ = k * H(m),   verify : e(σ, G2) == e(H(m), P)
+// Signatures add up in G1, so a watcher can check a whole batch of receipts with one
+// aggregate signature and a single multi-pairing.
+
+// domain separation tags fed to Poseidon together with the message
+const DST_SIGN: u64 = 1;
+const DST_POP: u64 = 2;
+
+// == Hash to G1 == //
+
+// 32 bytes -> two 128-bit limbs, so any byte string maps to field elements without reduction
+fn bytes_to_base(bytes: &[u8; 32]) -> [Base; 2] {
+    let hi = u128::from_be_bytes(bytes[..16].try_into().unwrap());
+    let lo = u128::from_be_bytes(bytes[16..].try_into().unwrap());
+    [Base::from_u128(hi), Base::from_u128(lo)]
+}
+
+// Try-and-increment : x = H(dst || m || ctr) until x^3 + b is a square.
+// G1 has cofactor 1, so every point on the curve is in the prime order group.
+fn hash_to_g1(dst: u64, message: &[u8; 32]) -> G1Affine {
+    let [hi, lo] = bytes_to_base(message);
+    let mut ctr = 0u64;
+    loop {
+        let x = poseidon_hash(vec![Base::from(dst), hi, lo, Base::from(ctr)]);
+        let y2 = x.square() * x + G1Affine::b();
+        if let Some(y) = Option::<Base>::from(y2.sqrt()) {
+            // pick the "even" root so the point is unique
+            let y = if bool::from(y.is_odd()) { -y } else { y };
+            return G1Affine::from_xy(x, y).unwrap();
+        }
+        ctr += 1;
+    }
+}
+
+pub fn hash_to_curve(message: &[u8; 32]) -> G1Affine {
+    hash_to_g1(DST_SIGN, message)
+}
+
+
+// == Key pair == //
+
+#[derive(Clone)]
+pub struct SecretKey(Scalar);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey(G2Affine);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature(G1Affine);
+
+impl SecretKey {
+    pub fn random(rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        Self(Scalar::random(rng))
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey((G2::generator() * self.0).to_affine())
+    }
+
+    pub fn sign(&self, message: &[u8; 32]) -> Signature {
+        Signature((hash_to_curve(message) * self.0).to_affine())
+    }
+
+    // Proof of possession : a signature over the public key itself under a separate tag.
+    // Same-message aggregation is only safe for keys whose possession was proven (rogue key attack).
+    pub fn prove_possession(&self) -> Signature {
+        let pk = self.public_key();
+        Signature((hash_to_g1(DST_POP, &pk.digest()) * self.0).to_affine())
+    }
+}
+
+impl PublicKey {
+    pub fn inner(&self) -> G2Affine {
+        self.0
+    }
+
+    // 32 bytes binding the public key, used as the proof of possession message
+    fn digest(&self) -> [u8; 32] {
+        let bytes = self.0.to_bytes();
+        let limbs = bytes
+            .as_ref()
+            .chunks(16)
+            .map(|chunk| {
+                let mut limb = [0u8; 16];
+                limb[..chunk.len()].copy_from_slice(chunk);
+                Base::from_u128(u128::from_be_bytes(limb))
+            })
+            .collect::<Vec<Base>>();
+        poseidon_hash(limbs).to_bytes()
+    }
+
+    pub fn verify(&self, message: &[u8; 32], signature: &Signature) -> bool {
+        pairing_check(signature, &[(hash_to_curve(message), self.0)])
+    }
+
+    pub fn verify_possession(&self, pop: &Signature) -> bool {
+        pairing_check(pop, &[(hash_to_g1(DST_POP, &self.digest()), self.0)])
+    }
+}
+
+impl Signature {
+    pub fn inner(&self) -> G1Affine {
+        self.0
+    }
+}
+
+// e(σ, G2) == Π e(h_i, P_i), computed as one multi Miller loop : e(σ, -G2) * Π e(h_i, P_i) == 1
+fn pairing_check(signature: &Signature, terms: &[(G1Affine, G2Affine)]) -> bool {
+    let neg_g2 = G2Prepared::from(-G2::generator().to_affine());
+    let prepared = terms
+        .iter()
+        .map(|(h, pk)| (h, G2Prepared::from(*pk)))
+        .collect::<Vec<_>>();
+
+    let mut loop_terms = vec![(&signature.0, &neg_g2)];
+    loop_terms.extend(prepared.iter().map(|(h, pk)| (*h, pk)));
+
+    let result = Bn256::multi_miller_loop(&loop_terms).final_exponentiation();
+    bool::from(result.is_identity())
+}
+
+
+// == Aggregation == //
+
+pub fn aggregate_signatures(signatures: &[Signature]) -> Signature {
+    let sum = signatures.iter().fold(G1::identity(), |acc, sig| acc + sig.0);
+    Signature(sum.to_affine())
+}
+
+pub fn aggregate_public_keys(keys: &[PublicKey]) -> PublicKey {
+    let sum = keys.iter().fold(G2::identity(), |acc, pk| acc + pk.0);
+    PublicKey(sum.to_affine())
+}
+
+// Every signer signed the same message : e(σ, G2) == e(H(m), Σ P_i).
+// The caller must have checked a proof of possession for every key.
+pub fn verify_same_message(keys: &[PublicKey], message: &[u8; 32], aggregate: &Signature) -> bool {
+    !keys.is_empty() && aggregate_public_keys(keys).verify(message, aggregate)
+}
+
+// Every signer signed its own message : e(σ, G2) == Π e(H(m_i), P_i).
+// Messages must be pairwise distinct, otherwise the check degenerates into the same message case.
+pub fn verify_distinct_messages(items: &[(PublicKey, [u8; 32])], aggregate: &Signature) -> bool {
+    let distinct = items.iter().map(|(_, m)| m).collect::<HashSet<_>>().len() == items.len();
+    if items.is_empty() || !distinct {
+        return false;
+    }
+
+    let terms = items
+        .iter()
+        .map(|(pk, message)| (hash_to_curve(message), pk.0))
+        .collect::<Vec<_>>();
+    pairing_check(aggregate, &terms)
+}
+
+
+#[test]
+pub fn test_bls_sig() {
+    use rand::rngs::OsRng;
+
+    let secret = SecretKey::random(&mut OsRng);
+    let public = secret.public_key();
+    let message: &[u8; 32] = &[1; 32];
+    let signature = secret.sign(message);
+
+    assert!(public.verify(message, &signature));
+    assert!(!public.verify(&[2; 32], &signature));
+    assert!(!SecretKey::random(&mut OsRng).public_key().verify(message, &signature));
+
+    assert!(public.verify_possession(&secret.prove_possession()));
+    assert!(!public.verify_possession(&signature));
+}
+
+#[test]
+pub fn test_bls_same_message_aggregation() {
+    use rand::rngs::OsRng;
+
+    let secrets = (0..8).map(|_| SecretKey::random(&mut OsRng)).collect::<Vec<SecretKey>>();
+    let keys = secrets.iter().map(|k| k.public_key()).collect::<Vec<PublicKey>>();
+    let message: &[u8; 32] = &[7; 32];
+
+    let aggregate = aggregate_signatures(&secrets.iter().map(|k| k.sign(message)).collect::<Vec<_>>());
+    assert!(verify_same_message(&keys, message, &aggregate));
+    assert!(!verify_same_message(&keys[1..], message, &aggregate));
+    assert!(!verify_same_message(&keys, &[8; 32], &aggregate));
+}
+
+#[test]
+pub fn test_bls_distinct_message_aggregation() {
+    use rand::rngs::OsRng;
+
+    // one receipt per sequenced index
+    let sequencer = SecretKey::random(&mut OsRng);
+    let mut items = vec![];
+    let mut signatures = vec![];
+    for i in 0..16u8 {
+        let message = [i; 32];
+        signatures.push(sequencer.sign(&message));
+        items.push((sequencer.public_key(), message));
+    }
+    let aggregate = aggregate_signatures(&signatures);
+    assert!(verify_distinct_messages(&items, &aggregate));
+
+    // swapping a single receipt is detected
+    let mut tampered = items.clone();
+    tampered[5].1 = [99; 32];
+    assert!(!verify_distinct_messages(&tampered, &aggregate));
+
+    // duplicated messages are rejected
+    let mut duplicated = items.clone();
+    duplicated[1].1 = duplicated[0].1;
+    assert!(!verify_distinct_messages(&duplicated, &aggregate));
+}
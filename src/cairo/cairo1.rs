@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use cairo_lang_casm::hints::Hint;
+use cairo_lang_starknet::casm_contract_class::{CasmContractClass, CasmContractEntryPoint};
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::cairo_1_hint_processor::hint_processor::Cairo1HintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessor;
+use cairo_vm::serde::deserialize_program::BuiltinName;
+use cairo_vm::types::errors::program_errors::ProgramError;
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::memory_errors::MemoryError;
+use cairo_vm::vm::errors::runner_errors::RunnerError;
+use cairo_vm::vm::runners::cairo_runner::{CairoArg, CairoRunner, RunResources};
+use cairo_vm::vm::vm_core::VirtualMachine;
+use num_bigint::BigUint;
+use sha3::{Digest, Keccak256};
+
+use super::{CairoError, CairoRun, Layout, Value};
+
+// Runs the external functions of a Cairo 1 contract compiled to CASM (the
+// compiled_contract_class.json of starknet-sierra-compile).
+//   let contract = CasmContract::from_file(path)?;
+//   let run = contract.run("dispute", &claim.to_felts())?;
+//   run.return_values()   : the retdata, as felts
+// Functions take their serialized arguments as calldata, a panic (e.g. 'Out of gas', or calldata
+// that doesn't deserialize) is returned as CairoError::Panic. run serves no syscall, contracts
+// that use them run in a StarknetState (see starknet.rs).
+
+// == Entrypoints == //
+
+// An external function of the contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CasmEntrypoint {
+    pub name: String,
+    pub selector: Felt252,
+    pub offset: usize,
+    // builtins taken before the gas and the syscall pointer (e.g. ["pedersen", "range_check"])
+    pub builtins: Vec<String>,
+}
+
+// keccak256 truncated to 250 bits : the selector of a function, the address of a storage variable
+pub fn sn_keccak(name: &str) -> Felt252 {
+    let mut hash = Keccak256::digest(name.as_bytes());
+    hash[0] &= 0x03;
+    Felt252::from(BigUint::from_bytes_be(&hash))
+}
+
+fn builtin_name(name: &str) -> Result<BuiltinName, CairoError> {
+    Ok(match name {
+        "output" => BuiltinName::output,
+        "range_check" => BuiltinName::range_check,
+        "pedersen" => BuiltinName::pedersen,
+        "ecdsa" => BuiltinName::ecdsa,
+        "keccak" => BuiltinName::keccak,
+        "bitwise" => BuiltinName::bitwise,
+        "ec_op" => BuiltinName::ec_op,
+        "poseidon" => BuiltinName::poseidon,
+        "segment_arena" => BuiltinName::segment_arena,
+        _ => return Err(CairoError::MissingBuiltin(name.to_string())),
+    })
+}
+
+
+// == Contract == //
+
+pub struct CasmContract {
+    class: CasmContractClass,
+    program: Program,
+    layout: Layout,
+}
+
+impl CasmContract {
+    pub fn new(class: CasmContractClass) -> Result<Self, CairoError> {
+        let program = Program::try_from(class.clone())?;
+        Ok(Self { class, program, layout: Layout::AllCairo })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, CairoError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CairoError> {
+        Self::new(serde_json::from_slice(bytes).map_err(ProgramError::from)?)
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn class(&self) -> &CasmContractClass {
+        &self.class
+    }
+
+    // compiler hints by pc, what Cairo1HintProcessor needs
+    pub fn hints(&self) -> &[(usize, Vec<Hint>)] {
+        &self.class.hints
+    }
+
+    // the class only has selectors, 'name' is hashed to find its function
+    pub fn entrypoint(&self, name: &str) -> Result<CasmEntrypoint, CairoError> {
+        let selector = sn_keccak(name);
+        let CasmContractEntryPoint { offset, builtins, .. } = self
+            .class
+            .entry_points_by_type
+            .external
+            .iter()
+            .find(|entrypoint| Felt252::from(entrypoint.selector.clone()) == selector)
+            .ok_or_else(|| CairoError::EntrypointNotFound(name.to_string()))?;
+        Ok(CasmEntrypoint { name: name.to_string(), selector, offset: *offset, builtins: builtins.clone() })
+    }
+
+    pub fn run(&self, name: &str, calldata: &[Felt252]) -> Result<CairoRun, CairoError> {
+        self.run_with_hints(name, calldata, &mut Cairo1HintProcessor::new(self.hints(), RunResources::default()))
+    }
+
+    // 'hint_processor' has to handle the compiler hints of the class, see hints()
+    pub fn run_with_hints(
+        &self,
+        name: &str,
+        calldata: &[Felt252],
+        hint_processor: &mut dyn HintProcessor,
+    ) -> Result<CairoRun, CairoError> {
+        let entrypoint = self.entrypoint(name)?;
+        let builtins = entrypoint.builtins.iter().map(|name| builtin_name(name)).collect::<Result<Vec<_>, _>>()?;
+
+        let mut vm = VirtualMachine::new(false);
+        let mut runner = CairoRunner::new(&self.program, self.layout.as_str(), false)?;
+        runner.initialize_function_runner_cairo_1(&mut vm, &builtins)?;
+
+        // implicit arguments : the builtins, the gas (unlimited) and the syscall pointer
+        let mut cairo_args = vec![];
+        for (name, builtin) in entrypoint.builtins.iter().zip(&builtins) {
+            let base = vm
+                .get_builtin_runners()
+                .iter()
+                .find(|runner| runner.name() == builtin.name())
+                .map(|runner| runner.base())
+                .ok_or_else(|| CairoError::MissingBuiltin(name.clone()))?;
+            cairo_args.push(CairoArg::Single(Relocatable::from((base as isize, 0)).into()));
+        }
+        cairo_args.push(CairoArg::Single(MaybeRelocatable::from(usize::MAX)));
+        cairo_args.push(CairoArg::Single(vm.add_memory_segment().into()));
+
+        // the gas costs of the builtins are read through a pointer stored after the bytecode,
+        // all zero : only the steps are limited
+        let costs = vm.add_memory_segment();
+        vm.load_data(costs, &vec![MaybeRelocatable::from(0); 5])?;
+        let program_base = runner.program_base.ok_or(RunnerError::NoProgBase)?;
+        let extra_data = vec![MaybeRelocatable::from(Felt252::from(0x208B7FFF7FFF7FFEu64)), costs.into()];
+        let program_end = (program_base + self.class.bytecode.len()).map_err(MemoryError::from)?;
+        vm.load_data(program_end, &extra_data)?;
+
+        // calldata, passed as a span (start, end)
+        let start = vm.add_memory_segment();
+        let end = vm.load_data(start, &calldata.iter().cloned().map(MaybeRelocatable::from).collect())?;
+        cairo_args.push(CairoArg::Single(start.into()));
+        cairo_args.push(CairoArg::Single(end.into()));
+        let cairo_args = cairo_args.iter().collect::<Vec<_>>();
+
+        let program_size = self.class.bytecode.len() + extra_data.len();
+        runner.run_from_entrypoint(entrypoint.offset, &cairo_args, true, Some(program_size), &mut vm, hint_processor)?;
+
+        // [panicked, retdata start, retdata end] after the builtins, the gas and the syscall pointer
+        let values = vm.get_return_values(3)?;
+        let (start, end) = (values[1].get_relocatable(), values[2].get_relocatable());
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err(CairoError::UnsupportedType("retdata is not a span".to_string())),
+        };
+        let retdata = vm
+            .get_integer_range(start, (end - start).map_err(MemoryError::from)?)?
+            .into_iter()
+            .map(|felt| felt.into_owned())
+            .collect::<Vec<_>>();
+        if values[0] != MaybeRelocatable::from(0) {
+            return Err(CairoError::Panic(retdata));
+        }
+
+        let return_values = retdata.into_iter().map(Value::Felt).collect();
+        Ok(CairoRun { runner, vm, return_values })
+    }
+}
+
+
+// The tests running cairo_program/cairo1/dispute_contract.cairo need its build :
+//   cairo_program/compile.sh && cargo test cairo1 -- --ignored
+#[cfg(test)]
+fn dispute_contract() -> CasmContract {
+    super::registry::contract("dispute_contract_cairo1").unwrap()
+}
+
+#[test]
+#[ignore]
+pub fn test_run_cairo1() {
+    use super::starknet::short_string;
+
+    let contract = dispute_contract();
+    let entrypoint = contract.entrypoint("dispute").unwrap();
+    assert_eq!(entrypoint.builtins, vec!["pedersen", "range_check", "ec_op"]);
+    assert_eq!(entrypoint.selector, sn_keccak("dispute"));
+    assert!(matches!(contract.run("missing", &[]), Err(CairoError::EntrypointNotFound(_))));
+
+    // calldata is checked before any syscall
+    let err = contract.run("register", &[Felt252::from(1)]).err().unwrap();
+    assert!(matches!(&err, CairoError::Panic(data) if data == &[short_string("Failed to deserialize param #1")]));
+    assert_eq!(err.to_string(), "panicked with 'Failed to deserialize param #1'");
+    let calldata = [Felt252::from(1), Felt252::from(2), Felt252::from(3)];
+    let err = contract.run("register", &calldata).err().unwrap();
+    assert!(matches!(err, CairoError::Panic(data) if data == [short_string("Input too long for arguments")]));
+
+    // reading the storage needs a StarknetState
+    assert!(matches!(contract.run("slashed", &[]), Err(CairoError::Run(_))));
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+use cairo_vm::felt::Felt252;
+use halo2_pse::halo2curves::ff::{Field, PrimeField};
+use halo2_pse::halo2curves::CurveAffine;
+use num_bigint::BigUint;
+
+// BN254 values as Cairo sees them. Fq and Fr are 254-bit, one felt holds less than 2^251 + 2^196,
+// so a field element travels as a Uint256 : two felts holding the low and high 128 bits of its
+// little-endian representation, a point as the four felts (x.low, x.high, y.low, y.high).
+// Reading back checks every range the Cairo side cannot be trusted with : limbs below 2^128,
+// the value below the field modulus, the point on the curve.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeltError {
+    // a limb does not fit in 128 bits
+    LimbOutOfRange(Felt252),
+    // the 256-bit value is not smaller than the field modulus
+    NotCanonical(Uint256),
+    // the value does not fit in a single felt
+    FeltOverflow,
+    NotOnCurve,
+    WrongLength { expected: usize, given: usize },
+}
+
+impl fmt::Display for FeltError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeltError::LimbOutOfRange(limb) => write!(f, "limb {} is not a 128-bit value", limb),
+            FeltError::NotCanonical(value) => write!(f, "{} is not a canonical field element", value),
+            FeltError::FeltOverflow => write!(f, "value does not fit in a felt"),
+            FeltError::NotOnCurve => write!(f, "point is not on the curve"),
+            FeltError::WrongLength { expected, given } => write!(f, "expected {} felts, {} given", expected, given),
+        }
+    }
+}
+
+impl std::error::Error for FeltError {}
+
+
+// == Field elements == //
+
+// Cairo's Uint256 : value = low + high * 2^128
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uint256 {
+    pub low: u128,
+    pub high: u128,
+}
+
+impl Uint256 {
+    pub fn from_field<F: PrimeField<Repr = [u8; 32]>>(x: &F) -> Self {
+        let repr = x.to_repr();
+        Self {
+            low: u128::from_le_bytes(repr[..16].try_into().unwrap()),
+            high: u128::from_le_bytes(repr[16..].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_field<F: PrimeField<Repr = [u8; 32]>>(self) -> Result<F, FeltError> {
+        let mut repr = [0u8; 32];
+        repr[..16].copy_from_slice(&self.low.to_le_bytes());
+        repr[16..].copy_from_slice(&self.high.to_le_bytes());
+        Option::from(F::from_repr(repr)).ok_or(FeltError::NotCanonical(self))
+    }
+
+    pub fn to_felts(self) -> [Felt252; 2] {
+        [Felt252::from(self.low), Felt252::from(self.high)]
+    }
+
+    pub fn from_felts(low: &Felt252, high: &Felt252) -> Result<Self, FeltError> {
+        Ok(Self { low: limb(low)?, high: limb(high)? })
+    }
+}
+
+impl fmt::Display for Uint256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Uint256(low={}, high={})", self.low, self.high)
+    }
+}
+
+fn limb(felt: &Felt252) -> Result<u128, FeltError> {
+    u128::try_from(felt.to_biguint()).map_err(|_| FeltError::LimbOutOfRange(felt.clone()))
+}
+
+// A field element small enough to be a single felt (hashes truncated to 251 bits, indices, ...)
+pub fn to_felt<F: PrimeField<Repr = [u8; 32]>>(x: &F) -> Result<Felt252, FeltError> {
+    let value = BigUint::from_bytes_le(x.to_repr().as_ref());
+    if value >= Felt252::prime() {
+        return Err(FeltError::FeltOverflow);
+    }
+    Ok(Felt252::from(value))
+}
+
+pub fn from_felt<F: PrimeField<Repr = [u8; 32]>>(felt: &Felt252) -> Result<F, FeltError> {
+    let bytes = felt.to_biguint().to_bytes_le();
+    let mut repr = [0u8; 32];
+    repr[..bytes.len()].copy_from_slice(&bytes);
+    Option::from(F::from_repr(repr)).ok_or(FeltError::FeltOverflow)
+}
+
+pub fn field_to_felts<F: PrimeField<Repr = [u8; 32]>>(x: &F) -> [Felt252; 2] {
+    Uint256::from_field(x).to_felts()
+}
+
+pub fn field_from_felts<F: PrimeField<Repr = [u8; 32]>>(felts: &[Felt252]) -> Result<F, FeltError> {
+    match felts {
+        [low, high] => Uint256::from_felts(low, high)?.to_field(),
+        _ => Err(FeltError::WrongLength { expected: 2, given: felts.len() }),
+    }
+}
+
+
+// == Points == //
+
+// (x.low, x.high, y.low, y.high), the identity being (0, 0)
+pub fn point_to_felts<C: CurveAffine>(point: &C) -> [Felt252; 4]
+where
+    C::Base: PrimeField<Repr = [u8; 32]>,
+{
+    let coords = point.coordinates();
+    let (x, y) = if bool::from(coords.is_some()) {
+        let coords = coords.unwrap();
+        (*coords.x(), *coords.y())
+    } else {
+        (C::Base::ZERO, C::Base::ZERO)
+    };
+    let ([x_low, x_high], [y_low, y_high]) = (field_to_felts(&x), field_to_felts(&y));
+    [x_low, x_high, y_low, y_high]
+}
+
+pub fn point_from_felts<C: CurveAffine>(felts: &[Felt252]) -> Result<C, FeltError>
+where
+    C::Base: PrimeField<Repr = [u8; 32]>,
+{
+    if felts.len() != 4 {
+        return Err(FeltError::WrongLength { expected: 4, given: felts.len() });
+    }
+    let x: C::Base = field_from_felts(&felts[..2])?;
+    let y: C::Base = field_from_felts(&felts[2..])?;
+    if bool::from(x.is_zero() & y.is_zero()) {
+        return Ok(C::identity());
+    }
+    Option::from(C::from_xy(x, y)).ok_or(FeltError::NotOnCurve)
+}
+
+
+// writes 'felts' in a fresh segment of a VM and reads them back, as a Cairo program would see them
+#[cfg(test)]
+fn through_memory(felts: &[Felt252]) -> Vec<Felt252> {
+    use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+    use cairo_vm::vm::vm_core::VirtualMachine;
+
+    let mut vm = VirtualMachine::new(false);
+    let base = vm.add_memory_segment();
+    let data = felts.iter().cloned().map(MaybeRelocatable::from).collect::<Vec<_>>();
+    vm.load_data(base, &data).unwrap();
+    (0..felts.len())
+        .map(|i| vm.get_integer(Relocatable::from((base.segment_index, base.offset + i))).unwrap().into_owned())
+        .collect()
+}
+
+#[test]
+pub fn test_field_round_trip() {
+    use halo2_pse::halo2curves::bn256::{Fq, Fr};
+    use halo2_pse::halo2curves::ff::Field;
+    use rand::rngs::OsRng;
+
+    for x in [Fq::ZERO, Fq::ONE, -Fq::ONE, Fq::random(OsRng)] {
+        let felts = through_memory(&field_to_felts(&x));
+        assert_eq!(field_from_felts::<Fq>(&felts).unwrap(), x);
+    }
+    for x in [Fr::ZERO, -Fr::ONE, Fr::random(OsRng)] {
+        let felts = through_memory(&field_to_felts(&x));
+        assert_eq!(field_from_felts::<Fr>(&felts).unwrap(), x);
+    }
+
+    // the top limb of -1 is above 2^125, the limbs stay below 2^128
+    let Uint256 { high, .. } = Uint256::from_field(&-Fq::ONE);
+    assert_eq!(high >> 125, 0b1);
+
+    // single felts, when the value fits
+    let x = Fr::from(u64::MAX);
+    assert_eq!(from_felt::<Fr>(&through_memory(&[to_felt(&x).unwrap()])[0]).unwrap(), x);
+    assert_eq!(to_felt(&-Fr::ONE), Err(FeltError::FeltOverflow));
+}
+
+#[test]
+pub fn test_point_round_trip() {
+    use halo2_pse::halo2curves::bn256::{Fr, G1Affine};
+    use halo2_pse::halo2curves::ff::Field;
+    use halo2_pse::halo2curves::group::prime::PrimeCurveAffine;
+    use halo2_pse::halo2curves::group::Curve;
+    use rand::rngs::OsRng;
+
+    use crate::schnorr::nk_generator;
+
+    let point = (nk_generator() * Fr::random(OsRng)).to_affine();
+    for point in [point, nk_generator(), G1Affine::identity()] {
+        let felts = through_memory(&point_to_felts(&point));
+        assert_eq!(point_from_felts::<G1Affine>(&felts).unwrap(), point);
+    }
+
+    // y + 1 is off the curve
+    let mut felts = point_to_felts(&point).to_vec();
+    felts[2] = felts[2].clone() + Felt252::from(1);
+    assert_eq!(point_from_felts::<G1Affine>(&felts), Err(FeltError::NotOnCurve));
+    assert!(matches!(point_from_felts::<G1Affine>(&felts[..3]), Err(FeltError::WrongLength { .. })));
+}
+
+#[test]
+pub fn test_range_checks() {
+    use halo2_pse::halo2curves::bn256::Fq;
+    use halo2_pse::halo2curves::ff::Field;
+
+    // limbs of 128 bits or more
+    let big = Felt252::from(u128::MAX) + Felt252::from(1);
+    assert_eq!(Uint256::from_felts(&big, &Felt252::from(0)), Err(FeltError::LimbOutOfRange(big.clone())));
+    assert!(matches!(field_from_felts::<Fq>(&[Felt252::from(0), big]), Err(FeltError::LimbOutOfRange(_))));
+
+    // the modulus itself and 2^256 - 1 are not canonical
+    let modulus = Uint256::from_field(&-Fq::ONE);
+    let modulus = Uint256 { low: modulus.low + 1, ..modulus };
+    assert_eq!(modulus.to_field::<Fq>(), Err(FeltError::NotCanonical(modulus)));
+    let max = Uint256 { low: u128::MAX, high: u128::MAX };
+    assert!(max.to_field::<Fq>().is_err());
+}
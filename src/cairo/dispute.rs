@@ -0,0 +1,406 @@
+use cairo_vm::felt::Felt252;
+use num_bigint::BigUint;
+use rand::{CryptoRng, RngCore};
+use starknet_crypto::{pedersen_hash, FieldElement};
+use starknet_curve::curve_params::{EC_ORDER, GENERATOR};
+use starknet_curve::AffinePoint;
+
+use super::fees::ResourceReport;
+use super::trace::Trace;
+use super::{registry, Argument, CairoError, CairoRun, Value};
+
+// Dispute verification of the "Smart Contract" step of main.rs, run through cairo-vm.
+// cairo_program/dispute.cairo is the contract logic on the STARK curve with Pedersen hashes,
+// this module builds the same transcript and batch trees natively so a claim can be checked
+// before it is sent :
+//   let claim = cosign(&mut OsRng, &user, &sequencer, message, index);
+//   let opening = Batch::new(&posted_messages).open(index);   // against the root the sequencer posted
+//   let fraud = run_dispute(&claim, &opening)?;   // Err if the contract rejects the claim
+// The program is built by cairo_program/compile.sh (see registry.rs). These co-signatures are
+// separate from the BN254/Poseidon receipts of schnorr.rs, which this program can't verify.
+
+// == STARK curve == //
+
+fn to_biguint(x: &FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&x.to_bytes_be())
+}
+
+// x must be smaller than the field modulus (scalars are, the curve order is below it)
+fn to_field(x: &BigUint) -> FieldElement {
+    let bytes = x.to_bytes_be();
+    let mut repr = [0u8; 32];
+    repr[32 - bytes.len()..].copy_from_slice(&bytes);
+    FieldElement::from_bytes_be(&repr).unwrap()
+}
+
+pub fn to_felt(x: &FieldElement) -> Felt252 {
+    Felt252::from(to_biguint(x))
+}
+
+fn order() -> BigUint {
+    to_biguint(&EC_ORDER)
+}
+
+pub fn random_scalar(rng: &mut (impl CryptoRng + RngCore)) -> BigUint {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    BigUint::from_bytes_be(&bytes) % order()
+}
+
+pub fn mul(point: &AffinePoint, k: &BigUint) -> AffinePoint {
+    point * &to_field(&(k % order())).to_bits_le()[..]
+}
+
+// StarkNet's compute_hash_on_elements : H(H(...H(H(0, x_1), x_2)..., x_n), n)
+pub fn hash_on_elements(elements: &[FieldElement]) -> FieldElement {
+    let acc = elements.iter().fold(FieldElement::ZERO, |acc, x| pedersen_hash(&acc, x));
+    pedersen_hash(&acc, &FieldElement::from(elements.len() as u64))
+}
+
+
+// == Batches == //
+
+// BATCH_DEPTH of dispute.cairo : a batch holds 2^32 consecutive indices
+pub const BATCH_DEPTH: usize = 32;
+
+// [0, H(0, 0), ...] : roots of the empty subtrees of each height
+fn empty_roots() -> Vec<FieldElement> {
+    let mut roots = vec![FieldElement::ZERO];
+    for _ in 0..BATCH_DEPTH {
+        let below = roots[roots.len() - 1];
+        roots.push(pedersen_hash(&below, &below));
+    }
+    roots
+}
+
+// The Merkle tree of the messages the sequencer included in a batch, in order : the sequencer
+// posts its root, a user opens the leaf at the index of a co-signature
+pub struct Batch {
+    // levels[0] are the messages, levels[BATCH_DEPTH] the root, only the non-empty nodes are kept
+    levels: Vec<Vec<FieldElement>>,
+    empty: Vec<FieldElement>,
+}
+
+// The leaf at an index of a batch and its siblings from the bottom up, with the batch number and
+// the root they open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchOpening {
+    pub batch: u64,
+    pub root: FieldElement,
+    pub leaf: FieldElement,
+    pub siblings: Vec<FieldElement>,
+}
+
+impl Batch {
+    // at most 2^BATCH_DEPTH messages
+    pub fn new(messages: &[FieldElement]) -> Self {
+        let empty = empty_roots();
+        let mut levels = vec![messages.to_vec()];
+        for empty_sibling in &empty[..BATCH_DEPTH] {
+            let parents = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| pedersen_hash(&pair[0], pair.get(1).unwrap_or(empty_sibling)))
+                .collect();
+            levels.push(parents);
+        }
+        Self { levels, empty }
+    }
+
+    pub fn root(&self) -> FieldElement {
+        self.levels[BATCH_DEPTH].first().copied().unwrap_or(self.empty[BATCH_DEPTH])
+    }
+
+    // the opening at 'index' as if this were the batch of 'index'
+    pub fn open(&self, index: u64) -> BatchOpening {
+        let position = (index % (1 << BATCH_DEPTH)) as usize;
+        let node = |height: usize, i: usize| self.levels[height].get(i).copied().unwrap_or(self.empty[height]);
+        BatchOpening {
+            batch: index >> BATCH_DEPTH,
+            root: self.root(),
+            leaf: node(0, position),
+            siblings: (0..BATCH_DEPTH).map(|height| node(height, (position >> height) ^ 1)).collect(),
+        }
+    }
+}
+
+impl BatchOpening {
+    // merkle_root of dispute.cairo : the leaf opens the root at 'index', which is in this batch
+    pub fn verify(&self, index: u64) -> bool {
+        let position = index % (1 << BATCH_DEPTH);
+        let root = self.siblings.iter().enumerate().fold(self.leaf, |node, (height, sibling)| {
+            if (position >> height) & 1 == 0 {
+                pedersen_hash(&node, sibling)
+            } else {
+                pedersen_hash(sibling, &node)
+            }
+        });
+        index >> BATCH_DEPTH == self.batch && self.siblings.len() == BATCH_DEPTH && root == self.root
+    }
+
+    fn arguments(&self) -> [Argument; 4] {
+        [
+            Argument::felt(self.batch),
+            Argument::Felt(to_felt(&self.root)),
+            Argument::Felt(to_felt(&self.leaf)),
+            Argument::Array(self.siblings.iter().map(to_felt).collect()),
+        ]
+    }
+}
+
+
+// == Claim == //
+
+// What the user submits : both public keys and nonces, the full and partial responses and the
+// message the sequencer co-signed at 'index'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claim {
+    pub user_key: AffinePoint,       // P_u
+    pub sequencer_key: AffinePoint,  // P_s
+    pub user_nonce: AffinePoint,     // R_u
+    pub nonce: AffinePoint,          // R = R_u + R_s
+    pub response: FieldElement,      // s
+    pub user_response: FieldElement, // s_u
+    pub message: FieldElement,
+    pub index: FieldElement,
+}
+
+// weights w_u, w_s and aggregated key X
+fn aggregate_key(user_key: &AffinePoint, sequencer_key: &AffinePoint) -> (FieldElement, FieldElement, AffinePoint) {
+    let l = hash_on_elements(&[user_key.x, user_key.y, sequencer_key.x, sequencer_key.y]);
+    let w_u = hash_on_elements(&[l, user_key.x, user_key.y]);
+    let w_s = hash_on_elements(&[l, sequencer_key.x, sequencer_key.y]);
+    let key = &mul(user_key, &to_biguint(&w_u)) + &mul(sequencer_key, &to_biguint(&w_s));
+    (w_u, w_s, key)
+}
+
+// e = H(R, X, m, i)
+fn challenge(nonce: &AffinePoint, key: &AffinePoint, message: FieldElement, index: FieldElement) -> FieldElement {
+    hash_on_elements(&[nonce.x, nonce.y, key.x, key.y, message, index])
+}
+
+// s_a = r_a + k_a * w_a * e (mod n)
+fn response(nonce: &BigUint, secret: &BigUint, weight: &FieldElement, challenge: &FieldElement) -> BigUint {
+    (nonce + secret * to_biguint(weight) % order() * to_biguint(challenge)) % order()
+}
+
+// An honest co-signature of 'message' at 'index' by the user and the sequencer
+pub fn cosign(
+    rng: &mut (impl CryptoRng + RngCore),
+    user: &BigUint,
+    sequencer: &BigUint,
+    message: FieldElement,
+    index: u64,
+) -> Claim {
+    let (user_key, sequencer_key) = (mul(&GENERATOR, user), mul(&GENERATOR, sequencer));
+    let (r_u, r_s) = (random_scalar(rng), random_scalar(rng));
+    let user_nonce = mul(&GENERATOR, &r_u);
+    let nonce = &user_nonce + &mul(&GENERATOR, &r_s);
+
+    let (w_u, w_s, key) = aggregate_key(&user_key, &sequencer_key);
+    let index = FieldElement::from(index);
+    let e = challenge(&nonce, &key, message, index);
+    let s_u = response(&r_u, user, &w_u, &e);
+    let s_s = response(&r_s, sequencer, &w_s, &e);
+
+    Claim {
+        user_key,
+        sequencer_key,
+        user_nonce,
+        nonce,
+        response: to_field(&((&s_u + &s_s) % order())),
+        user_response: to_field(&s_u),
+        message,
+        index,
+    }
+}
+
+impl Claim {
+    // (s - s_u) * G == (R - R_u) + e * (X - w_u * P_u), natively
+    pub fn verify(&self) -> bool {
+        let (w_u, _, key) = aggregate_key(&self.user_key, &self.sequencer_key);
+        let e = challenge(&self.nonce, &key, self.message, self.index);
+        let s = (to_biguint(&self.response) + order() - to_biguint(&self.user_response)) % order();
+        let left = mul(&GENERATOR, &s);
+        let right = &(&self.nonce - &self.user_nonce) + &mul(&(&key - &mul(&self.user_key, &to_biguint(&w_u))), &to_biguint(&e));
+        left == right
+    }
+
+    // What verify_claim of dispute.cairo returns, natively : None if the claim is rejected (the
+    // signature or the opening doesn't verify), Some(true) if the batch holds another message
+    pub fn is_fraud(&self, opening: &BatchOpening) -> Option<bool> {
+        let index = u64::try_from(to_biguint(&self.index)).ok()?;
+        if !self.verify() || !opening.verify(index) {
+            return None;
+        }
+        Some(opening.leaf != self.message)
+    }
+
+    // memory layout of the Cairo struct Claim
+    pub fn to_felts(&self) -> Vec<Felt252> {
+        [
+            self.user_key.x,
+            self.user_key.y,
+            self.sequencer_key.x,
+            self.sequencer_key.y,
+            self.user_nonce.x,
+            self.user_nonce.y,
+            self.nonce.x,
+            self.nonce.y,
+            self.response,
+            self.user_response,
+            self.message,
+            self.index,
+        ]
+        .iter()
+        .map(to_felt)
+        .collect()
+    }
+}
+
+fn dispute_arguments(claim: &Claim, opening: &BatchOpening) -> Vec<Argument> {
+    let mut args = vec![Argument::Array(claim.to_felts())];
+    args.extend(opening.arguments());
+    args
+}
+
+fn verify_claim(claim: &Claim, opening: &BatchOpening) -> Result<CairoRun, CairoError> {
+    let executor = registry::executor("dispute")?;
+    executor.run("verify_claim", &dispute_arguments(claim, opening))
+}
+
+// The verification step by step, a rejected claim shows where it fails (see trace.rs)
+pub fn trace_dispute(claim: &Claim, opening: &BatchOpening) -> Result<Trace, CairoError> {
+    let executor = registry::executor("dispute")?;
+    executor.trace("verify_claim", &dispute_arguments(claim, opening))
+}
+
+// Runs verify_claim of dispute.cairo : Ok(true) if the sequencer co-signed claim.message at
+// claim.index but the batch opened by 'opening' holds another message there, an error if the
+// claim or the opening does not verify
+pub fn run_dispute(claim: &Claim, opening: &BatchOpening) -> Result<bool, CairoError> {
+    let run = verify_claim(claim, opening)?;
+    Ok(run.return_values() == [Value::Felt(Felt252::from(1))])
+}
+
+// Steps and builtin instances of the verification (see fees.rs)
+pub fn dispute_resources(claim: &Claim, opening: &BatchOpening) -> Result<ResourceReport, CairoError> {
+    verify_claim(claim, opening)?.resources()
+}
+
+
+#[cfg(test)]
+fn messages(at_42: u64) -> Vec<FieldElement> {
+    (0..50u64).map(|i| FieldElement::from(if i == 42 { at_42 } else { 1000 + i })).collect()
+}
+
+#[test]
+pub fn test_batch_opening() {
+    let batch = Batch::new(&messages(7));
+    let opening = batch.open(42);
+    assert_eq!(opening.leaf, FieldElement::from(7u64));
+    assert!(opening.verify(42));
+    // the root of batch 0 opens nothing of batch 1, even at the same position
+    assert!(!opening.verify(42 + (1 << BATCH_DEPTH)));
+    assert!(!BatchOpening { batch: 1, ..opening.clone() }.verify(42));
+    let next = batch.open(42 + (1 << BATCH_DEPTH));
+    assert_eq!(next.batch, 1);
+    assert!(next.verify(42 + (1 << BATCH_DEPTH)));
+    assert!(!next.verify(42));
+
+    // an opening only verifies at its index, for its leaf
+    assert!(!opening.verify(43));
+    assert!(!BatchOpening { leaf: FieldElement::from(8u64), ..opening.clone() }.verify(42));
+    assert!(!BatchOpening { siblings: opening.siblings[1..].to_vec(), ..opening.clone() }.verify(42));
+
+    // empty slots open to 0
+    let empty = batch.open(1 << 20);
+    assert_eq!(empty.leaf, FieldElement::ZERO);
+    assert!(empty.verify(1 << 20));
+    assert_eq!(Batch::new(&[]).root(), empty_roots()[BATCH_DEPTH]);
+}
+
+#[test]
+pub fn test_claim_is_fraud() {
+    use rand::rngs::OsRng;
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42);
+    let honest = Batch::new(&messages(7));
+    assert_eq!(claim.is_fraud(&honest.open(42)), Some(false));
+
+    // the sequencer co-signed 7 at 42 but posted a batch with 8 there, or nothing
+    assert_eq!(claim.is_fraud(&Batch::new(&messages(8)).open(42)), Some(true));
+    assert_eq!(claim.is_fraud(&Batch::new(&messages(7)[..40]).open(42)), Some(true));
+
+    // an honest claim can't be made a fraud : the opening of another index, another leaf at 42
+    // or another root for the same siblings is rejected
+    assert_eq!(claim.is_fraud(&honest.open(43)), None);
+    assert_eq!(claim.is_fraud(&BatchOpening { leaf: FieldElement::from(8u64), ..honest.open(42) }), None);
+    let other_root = Batch::new(&messages(8)).root();
+    assert_eq!(claim.is_fraud(&BatchOpening { root: other_root, ..honest.open(42) }), None);
+
+    // nor the opening of 42 in another batch
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42 + (1 << BATCH_DEPTH));
+    assert_eq!(claim.is_fraud(&Batch::new(&messages(8)).open(42)), None);
+}
+
+// The tests running dispute.cairo need its build :
+//   cairo_program/compile.sh && cargo test dispute -- --ignored
+#[test]
+#[ignore]
+pub fn test_dispute_honest_sequencer() {
+    use rand::rngs::OsRng;
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42);
+    assert!(claim.verify());
+
+    // the message is where the sequencer committed it, nothing to dispute
+    let honest = Batch::new(&messages(7));
+    assert!(!run_dispute(&claim, &honest.open(42)).unwrap());
+
+    // and no opening makes it a fraud : the leaf of another index, or another leaf at 42
+    assert!(matches!(run_dispute(&claim, &honest.open(43)), Err(CairoError::Run(_))));
+    let forged = BatchOpening { leaf: FieldElement::from(8u64), ..honest.open(42) };
+    assert!(matches!(run_dispute(&claim, &forged), Err(CairoError::Run(_))));
+
+    // nor the root of another batch, opened at the same position
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42 + (1 << BATCH_DEPTH));
+    let other_batch = Batch::new(&messages(8)).open(42);
+    assert!(matches!(run_dispute(&claim, &other_batch), Err(CairoError::Run(_))));
+}
+
+#[test]
+#[ignore]
+pub fn test_dispute_dishonest_sequencer() {
+    use rand::rngs::OsRng;
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let message = FieldElement::from(7u64);
+    let claim = cosign(&mut OsRng, &user, &sequencer, message, 42);
+
+    // co-signed 7 at 42, posted a batch with 8 there
+    let opening = Batch::new(&messages(8)).open(42);
+    assert_eq!(claim.is_fraud(&opening), Some(true));
+    assert!(run_dispute(&claim, &opening).unwrap());
+
+    // a claim for another index, message or response than the co-signed one is rejected
+    let mut forged = claim.clone();
+    forged.index = FieldElement::from(43u64);
+    assert!(!forged.verify());
+    assert!(matches!(run_dispute(&forged, &Batch::new(&messages(8)).open(43)), Err(CairoError::Run(_))));
+
+    let mut forged = claim.clone();
+    forged.message = FieldElement::from(8u64);
+    assert!(matches!(run_dispute(&forged, &opening), Err(CairoError::Run(_))));
+
+    let mut forged = claim.clone();
+    forged.response += FieldElement::ONE;
+    assert!(matches!(run_dispute(&forged, &opening), Err(CairoError::Run(_))));
+
+    // a sequencer co-signing with another key than the one it registered
+    let other = cosign(&mut OsRng, &user, &random_scalar(&mut OsRng), message, 42);
+    let mut forged = claim;
+    forged.sequencer_key = other.sequencer_key;
+    assert!(matches!(run_dispute(&forged, &opening), Err(CairoError::Run(_))));
+}
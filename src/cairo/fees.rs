@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{CairoError, CairoRun};
+
+// What a run costs and what it would be charged on StarkNet.
+//   let resources = run.resources()?;              // steps and builtin instances
+//   let estimate = estimate_fee(&resources, &PriceTable::default());
+// The fee follows StarkNet's model : every resource is converted to L1 gas with its weight and
+// the transaction pays for the most expensive one only (the bottleneck of the proof), times the
+// gas price. The default weights are the ones of StarkNet 0.13, a JSON file can override any of them.
+
+// == Resources == //
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceReport {
+    pub steps: usize,
+    pub memory_holes: usize,
+    pub range_check: usize,
+    pub pedersen: usize,
+    pub poseidon: usize,
+    pub ec_op: usize,
+    pub bitwise: usize,
+    pub ecdsa: usize,
+    pub keccak: usize,
+}
+
+impl ResourceReport {
+    // (name, amount) of every charged resource, memory holes are not charged
+    pub fn usage(&self) -> [(&'static str, usize); 8] {
+        [
+            ("steps", self.steps),
+            ("range_check", self.range_check),
+            ("pedersen", self.pedersen),
+            ("poseidon", self.poseidon),
+            ("ec_op", self.ec_op),
+            ("bitwise", self.bitwise),
+            ("ecdsa", self.ecdsa),
+            ("keccak", self.keccak),
+        ]
+    }
+}
+
+impl CairoRun {
+    pub fn resources(&self) -> Result<ResourceReport, CairoError> {
+        let resources = self.runner.get_execution_resources(&self.vm)?;
+        let instances = |name: &str| resources.builtin_instance_counter.get(&format!("{}_builtin", name)).copied().unwrap_or(0);
+        Ok(ResourceReport {
+            steps: resources.n_steps,
+            memory_holes: resources.n_memory_holes,
+            range_check: instances("range_check"),
+            pedersen: instances("pedersen"),
+            poseidon: instances("poseidon"),
+            ec_op: instances("ec_op"),
+            bitwise: instances("bitwise"),
+            ecdsa: instances("ecdsa"),
+            keccak: instances("keccak"),
+        })
+    }
+}
+
+
+// == Fees == //
+
+// L1 gas per unit of each resource, and the price of the gas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceTable {
+    // wei per L1 gas
+    pub gas_price: u128,
+    pub steps: f64,
+    pub range_check: f64,
+    pub pedersen: f64,
+    pub poseidon: f64,
+    pub ec_op: f64,
+    pub bitwise: f64,
+    pub ecdsa: f64,
+    pub keccak: f64,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self {
+            gas_price: 30_000_000_000, // 30 gwei
+            steps: 0.0025,
+            range_check: 0.04,
+            pedersen: 0.08,
+            poseidon: 0.08,
+            ec_op: 2.56,
+            bitwise: 0.16,
+            ecdsa: 5.12,
+            keccak: 5.12,
+        }
+    }
+}
+
+impl PriceTable {
+    // e.g. {"gas_price": 20000000000, "ec_op": 1.28}, missing entries keep their default
+    pub fn from_file(path: &Path) -> Result<Self, CairoError> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| CairoError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    // same order as ResourceReport::usage
+    fn weights(&self) -> [f64; 8] {
+        [self.steps, self.range_check, self.pedersen, self.poseidon, self.ec_op, self.bitwise, self.ecdsa, self.keccak]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeEstimate {
+    pub resources: ResourceReport,
+    // L1 gas of each resource on its own
+    pub gas_by_resource: BTreeMap<&'static str, f64>,
+    // the resource the fee is charged on
+    pub bottleneck: &'static str,
+    pub gas: u128,
+    pub gas_price: u128,
+    // in wei
+    pub fee: u128,
+}
+
+pub fn estimate_fee(resources: &ResourceReport, prices: &PriceTable) -> FeeEstimate {
+    let gas_by_resource = resources
+        .usage()
+        .iter()
+        .zip(prices.weights())
+        .map(|((name, amount), weight)| (*name, *amount as f64 * weight))
+        .collect::<BTreeMap<_, _>>();
+    let (bottleneck, gas) = gas_by_resource
+        .iter()
+        .fold(("steps", 0.0), |max, (name, gas)| if *gas > max.1 { (*name, *gas) } else { max });
+    let gas = gas.ceil() as u128;
+
+    FeeEstimate {
+        resources: resources.clone(),
+        gas_by_resource,
+        bottleneck,
+        gas,
+        gas_price: prices.gas_price,
+        fee: gas * prices.gas_price,
+    }
+}
+
+// Fee of the dispute verification of a fraudulent claim, for the 'fees' command
+//   cargo run --release -- fees [prices.json]
+pub fn dispute_fee_json(prices: &PriceTable) -> Result<String, CairoError> {
+    use rand::rngs::OsRng;
+    use starknet_crypto::FieldElement;
+
+    use super::dispute::{cosign, dispute_resources, random_scalar, Batch};
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42);
+    // the sequencer posted 8 at 42
+    let opening = Batch::new(&[FieldElement::from(8u64); 43]).open(42);
+    let resources = dispute_resources(&claim, &opening)?;
+    Ok(serde_json::to_string_pretty(&estimate_fee(&resources, prices)).unwrap())
+}
+
+
+#[test]
+pub fn test_fee_model() {
+    let resources = ResourceReport { steps: 1000, pedersen: 20, ec_op: 6, ..Default::default() };
+
+    // 6 ec_op at 2.56 outweigh 1000 steps at 0.0025 and 20 pedersen at 0.08
+    let estimate = estimate_fee(&resources, &PriceTable::default());
+    assert_eq!(estimate.bottleneck, "ec_op");
+    assert_eq!(estimate.gas_by_resource["steps"], 2.5);
+    assert_eq!(estimate.gas, 16);
+    assert_eq!(estimate.fee, 16 * 30_000_000_000);
+
+    // a table where steps are expensive
+    let prices: PriceTable = serde_json::from_str(r#"{"gas_price": 1, "steps": 1.0}"#).unwrap();
+    assert_eq!(prices.ec_op, 2.56);
+    let estimate = estimate_fee(&resources, &prices);
+    assert_eq!((estimate.bottleneck, estimate.gas, estimate.fee), ("steps", 1000, 1000));
+
+    // nothing used, nothing paid
+    assert_eq!(estimate_fee(&ResourceReport::default(), &prices).fee, 0);
+}
+
+// needs the build of dispute.cairo, see dispute.rs
+#[test]
+#[ignore]
+pub fn test_dispute_resources() {
+    use rand::rngs::OsRng;
+    use starknet_crypto::FieldElement;
+
+    use super::dispute::{cosign, dispute_resources, random_scalar, Batch, BATCH_DEPTH};
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42);
+    let resources = dispute_resources(&claim, &Batch::new(&[FieldElement::from(8u64); 43]).open(42)).unwrap();
+    // hash_on_elements of 4, 3, 3 and 6 elements, one hash per element plus the length, and one
+    // hash per level of the batch opening
+    assert_eq!(resources.pedersen, 5 + 4 + 4 + 7 + BATCH_DEPTH);
+    assert_eq!(resources.ec_op, 6);
+    assert!(resources.range_check > 0);
+    assert_eq!(resources.poseidon, 0);
+    assert!(resources.steps > 0);
+
+    // honest or not, the verification costs the same
+    let honest = Batch::new(&[FieldElement::from(7u64); 43]).open(42);
+    assert_eq!(dispute_resources(&claim, &honest).unwrap(), resources);
+
+    let json: serde_json::Value = serde_json::from_str(&dispute_fee_json(&PriceTable::default()).unwrap()).unwrap();
+    assert_eq!(json["bottleneck"], "ec_op");
+    assert_eq!(json["resources"]["ec_op"], 6);
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::{BuiltinHintProcessor, HintFunc};
+use cairo_vm::hint_processor::builtin_hint_processor::hint_utils::{
+    get_integer_from_var_name, get_ptr_from_var_name, get_relocatable_from_var_name,
+};
+use cairo_vm::hint_processor::hint_processor_definition::HintReference;
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use halo2_pse::halo2curves::bn256::{Fq, Fr, G1Affine};
+use halo2_pse::halo2curves::group::Curve;
+
+use super::convert::{field_from_felts, field_to_felts, point_from_felts, point_to_felts, FeltError};
+use crate::halo2::poseidon_hash;
+use crate::schnorr::musig_coefficients;
+
+// Hints computing with the sequencer's cryptography during a Cairo run, on top of the standard
+// hints of cairo-vm. A hint is found by its exact code (see cairo_program/sequencer_hints.cairo) :
+//   let mut hints = sequencer_hint_processor();
+//   executor.run_with_hints("bn254_poseidon", &args, &mut hints)?;
+// BN254 values are read and written in their Uint256 form (see convert.rs).
+
+pub const BN254_POSEIDON: &str = "bn254_poseidon(ids.inputs, ids.n_inputs, ids.res)";
+pub const MUSIG_COEFFICIENTS: &str = "musig_coefficients(ids.user_key, ids.sequencer_key, ids.w_u, ids.w_s)";
+pub const BN254_EC_ADD: &str = "bn254_ec_add(ids.p, ids.q, ids.res)";
+pub const BN254_EC_MUL: &str = "bn254_ec_mul(ids.p, ids.k, ids.res)";
+
+pub fn sequencer_hint_processor() -> BuiltinHintProcessor {
+    let mut processor = BuiltinHintProcessor::new_empty();
+    processor.add_hint(BN254_POSEIDON.to_string(), Rc::new(HintFunc(Box::new(bn254_poseidon))));
+    processor.add_hint(MUSIG_COEFFICIENTS.to_string(), Rc::new(HintFunc(Box::new(musig))));
+    processor.add_hint(BN254_EC_ADD.to_string(), Rc::new(HintFunc(Box::new(bn254_ec_add))));
+    processor.add_hint(BN254_EC_MUL.to_string(), Rc::new(HintFunc(Box::new(bn254_ec_mul))));
+    processor
+}
+
+
+// == Memory == //
+
+fn felt_error(e: FeltError) -> HintError {
+    HintError::CustomHint(e.to_string().into())
+}
+
+fn read_felts(vm: &VirtualMachine, ptr: Relocatable, len: usize) -> Result<Vec<Felt252>, HintError> {
+    (0..len)
+        .map(|i| Ok(vm.get_integer(Relocatable::from((ptr.segment_index, ptr.offset + i)))?.into_owned()))
+        .collect()
+}
+
+fn write_felts(vm: &mut VirtualMachine, ptr: Relocatable, felts: &[Felt252]) -> Result<(), HintError> {
+    for (i, felt) in felts.iter().enumerate() {
+        vm.insert_value(Relocatable::from((ptr.segment_index, ptr.offset + i)), felt.clone())?;
+    }
+    Ok(())
+}
+
+// the G1Point pointed to by ids.name
+fn read_point(name: &str, vm: &VirtualMachine, ids: &HashMap<String, HintReference>, ap: &ApTracking) -> Result<G1Affine, HintError> {
+    let ptr = get_ptr_from_var_name(name, vm, ids, ap)?;
+    point_from_felts(&read_felts(vm, ptr, 4)?).map_err(felt_error)
+}
+
+fn write_point(name: &str, point: &G1Affine, vm: &mut VirtualMachine, ids: &HashMap<String, HintReference>, ap: &ApTracking) -> Result<(), HintError> {
+    let address = get_relocatable_from_var_name(name, vm, ids, ap)?;
+    write_felts(vm, address, &point_to_felts(point))
+}
+
+fn write_field(name: &str, x: &Fq, vm: &mut VirtualMachine, ids: &HashMap<String, HintReference>, ap: &ApTracking) -> Result<(), HintError> {
+    let address = get_relocatable_from_var_name(name, vm, ids, ap)?;
+    write_felts(vm, address, &field_to_felts(x))
+}
+
+
+// == Hints == //
+
+// ids.res = Poseidon(ids.inputs[0..ids.n_inputs]) over Fq
+fn bn254_poseidon(
+    vm: &mut VirtualMachine,
+    _scopes: &mut ExecutionScopes,
+    ids: &HashMap<String, HintReference>,
+    ap: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let ptr = get_ptr_from_var_name("inputs", vm, ids, ap)?;
+    let n_inputs = get_integer_from_var_name("n_inputs", vm, ids, ap)?.to_biguint();
+    let n_inputs = usize::try_from(n_inputs).map_err(|_| HintError::CustomHint("n_inputs is too large".into()))?;
+    let inputs = read_felts(vm, ptr, 2 * n_inputs)?
+        .chunks(2)
+        .map(field_from_felts::<Fq>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(felt_error)?;
+    write_field("res", &poseidon_hash(inputs), vm, ids, ap)
+}
+
+// ids.w_u, ids.w_s = musig_coefficients(ids.user_key, ids.sequencer_key)
+fn musig(
+    vm: &mut VirtualMachine,
+    _scopes: &mut ExecutionScopes,
+    ids: &HashMap<String, HintReference>,
+    ap: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let user = read_point("user_key", vm, ids, ap)?;
+    let sequencer = read_point("sequencer_key", vm, ids, ap)?;
+    let (w_u, w_s) = musig_coefficients(&user, &sequencer);
+    write_field("w_u", &w_u, vm, ids, ap)?;
+    write_field("w_s", &w_s, vm, ids, ap)
+}
+
+// ids.res = ids.p + ids.q
+fn bn254_ec_add(
+    vm: &mut VirtualMachine,
+    _scopes: &mut ExecutionScopes,
+    ids: &HashMap<String, HintReference>,
+    ap: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let p = read_point("p", vm, ids, ap)?;
+    let q = read_point("q", vm, ids, ap)?;
+    write_point("res", &(p + q).to_affine(), vm, ids, ap)
+}
+
+// ids.res = ids.k * ids.p, k in Fr
+fn bn254_ec_mul(
+    vm: &mut VirtualMachine,
+    _scopes: &mut ExecutionScopes,
+    ids: &HashMap<String, HintReference>,
+    ap: &ApTracking,
+    _constants: &HashMap<String, Felt252>,
+) -> Result<(), HintError> {
+    let p = read_point("p", vm, ids, ap)?;
+    let address = get_relocatable_from_var_name("k", vm, ids, ap)?;
+    let k = field_from_felts::<Fr>(&read_felts(vm, address, 2)?).map_err(felt_error)?;
+    write_point("res", &(p * k).to_affine(), vm, ids, ap)
+}
+
+
+#[cfg(test)]
+fn sequencer_hints() -> super::CairoExecutor {
+    super::registry::executor("sequencer_hints").unwrap()
+}
+
+#[cfg(test)]
+fn returned_felts(run: &super::CairoRun) -> Vec<Felt252> {
+    run.return_values().iter().map(|value| value.as_felt().unwrap().clone()).collect()
+}
+
+// The tests running sequencer_hints.cairo need its build :
+//   cairo_program/compile.sh && cargo test hints -- --ignored
+#[test]
+#[ignore]
+pub fn test_poseidon_and_musig_hints() {
+    use halo2_pse::halo2curves::ff::Field;
+    use rand::rngs::OsRng;
+
+    use super::Argument;
+    use crate::schnorr::nk_generator;
+
+    let executor = sequencer_hints();
+    let mut hints = sequencer_hint_processor();
+
+    let inputs = (0..5).map(|_| Fq::random(OsRng)).collect::<Vec<_>>();
+    let args = [Argument::Array(inputs.iter().flat_map(field_to_felts).collect()), Argument::felt(inputs.len() as u64)];
+    let run = executor.run_with_hints("bn254_poseidon", &args, &mut hints).unwrap();
+    assert_eq!(field_from_felts::<Fq>(&returned_felts(&run)).unwrap(), poseidon_hash(inputs));
+
+    let user = (nk_generator() * Fr::random(OsRng)).to_affine();
+    let sequencer = (nk_generator() * Fr::random(OsRng)).to_affine();
+    let args = [Argument::Array(point_to_felts(&user).to_vec()), Argument::Array(point_to_felts(&sequencer).to_vec())];
+    let run = executor.run_with_hints("musig_coefficients", &args, &mut hints).unwrap();
+    let felts = returned_felts(&run);
+    let (w_u, w_s) = musig_coefficients(&user, &sequencer);
+    assert_eq!(field_from_felts::<Fq>(&felts[..2]).unwrap(), w_u);
+    assert_eq!(field_from_felts::<Fq>(&felts[2..]).unwrap(), w_s);
+
+    // the standard processor does not know these hints
+    assert!(executor.run("musig_coefficients", &args).is_err());
+}
+
+#[test]
+#[ignore]
+pub fn test_ec_hints() {
+    use halo2_pse::halo2curves::ff::Field;
+    use rand::rngs::OsRng;
+
+    use super::Argument;
+    use crate::schnorr::nk_generator;
+
+    let executor = sequencer_hints();
+    let mut hints = sequencer_hint_processor();
+
+    let (a, b) = (Fr::random(OsRng), Fr::random(OsRng));
+    let (p, q) = ((nk_generator() * a).to_affine(), (nk_generator() * b).to_affine());
+
+    let args = [Argument::Array(point_to_felts(&p).to_vec()), Argument::Array(point_to_felts(&q).to_vec())];
+    let run = executor.run_with_hints("bn254_ec_add", &args, &mut hints).unwrap();
+    let sum = point_from_felts::<G1Affine>(&returned_felts(&run)).unwrap();
+    assert_eq!(sum, (nk_generator() * (a + b)).to_affine());
+
+    let [low, high] = field_to_felts(&b);
+    let args = [Argument::Array(point_to_felts(&p).to_vec()), Argument::Felt(low), Argument::Felt(high)];
+    let run = executor.run_with_hints("bn254_ec_mul", &args, &mut hints).unwrap();
+    let product = point_from_felts::<G1Affine>(&returned_felts(&run)).unwrap();
+    assert_eq!(product, (nk_generator() * (a * b)).to_affine());
+
+    // a point off the curve is refused by the hint
+    let mut off_curve = point_to_felts(&p).to_vec();
+    off_curve[0] = off_curve[0].clone() + Felt252::from(1);
+    let args = [Argument::Array(off_curve), Argument::Array(point_to_felts(&q).to_vec())];
+    assert!(executor.run_with_hints("bn254_ec_add", &args, &mut hints).is_err());
+}
@@ -0,0 +1,509 @@
+pub mod cairo1;
+pub mod convert;
+pub mod dispute;
+pub mod fees;
+pub mod hints;
+pub mod proof;
+pub mod registry;
+pub mod starknet;
+pub mod trace;
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cairo_vm::air_public_input::PublicInputError;
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::BuiltinHintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessor;
+use cairo_vm::types::errors::program_errors::ProgramError;
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::memory_errors::MemoryError;
+use cairo_vm::vm::errors::runner_errors::RunnerError;
+use cairo_vm::vm::runners::builtin_runner::{BuiltinRunner, OUTPUT_BUILTIN_NAME};
+use cairo_vm::vm::runners::cairo_runner::{CairoArg, CairoRunner};
+use cairo_vm::vm::vm_core::VirtualMachine;
+
+// Runs any function of a compiled Cairo 0 program (cairo-compile JSON output).
+//   let executor = CairoExecutor::from_file(path)?.with_layout(Layout::AllCairo);
+//   let run = executor.run("compare_abs_arrays", &[array_a, array_b, len, 0])?;
+//   run.return_values()   : the declared return values, implicit arguments excluded
+//   run.output()          : what the function wrote through the output builtin
+// Implicit arguments (range_check_ptr, output_ptr, pedersen_ptr, ...) are bound to the
+// builtin segments automatically, only the explicit arguments are passed by the caller.
+
+// == Types == //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Plain,
+    Small,
+    Dex,
+    Starknet,
+    StarknetWithKeccak,
+    RecursiveLargeOutput,
+    AllCairo,
+    AllSolidity,
+}
+
+impl Layout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layout::Plain => "plain",
+            Layout::Small => "small",
+            Layout::Dex => "dex",
+            Layout::Starknet => "starknet",
+            Layout::StarknetWithKeccak => "starknet_with_keccak",
+            Layout::RecursiveLargeOutput => "recursive_large_output",
+            Layout::AllCairo => "all_cairo",
+            Layout::AllSolidity => "all_solidity",
+        }
+    }
+}
+
+// Explicit argument of an entrypoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Argument {
+    Felt(Felt252),
+    // loaded in a fresh segment, passed as a pointer to its first element
+    Array(Vec<Felt252>),
+    // a pointer to memory that already exists (e.g. returned by a previous call)
+    Pointer(Relocatable),
+    // a fresh segment holding the given arguments (struct or array of pointers)
+    Composed(Vec<Argument>),
+}
+
+impl Argument {
+    pub fn felt(value: impl Into<Felt252>) -> Self {
+        Argument::Felt(value.into())
+    }
+
+    pub fn array<T: Into<Felt252>>(values: impl IntoIterator<Item = T>) -> Self {
+        Argument::Array(values.into_iter().map(Into::into).collect())
+    }
+
+    fn to_cairo_arg(&self) -> CairoArg {
+        match self {
+            Argument::Felt(value) => CairoArg::Single(MaybeRelocatable::Int(value.clone())),
+            Argument::Array(values) => CairoArg::Array(values.iter().cloned().map(MaybeRelocatable::Int).collect()),
+            Argument::Pointer(ptr) => CairoArg::Single(MaybeRelocatable::RelocatableValue(*ptr)),
+            Argument::Composed(args) => CairoArg::Composed(args.iter().map(Argument::to_cairo_arg).collect()),
+        }
+    }
+}
+
+// A return value cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Felt(Felt252),
+    Pointer(Relocatable),
+}
+
+impl Value {
+    pub fn as_felt(&self) -> Option<&Felt252> {
+        match self {
+            Value::Felt(value) => Some(value),
+            Value::Pointer(_) => None,
+        }
+    }
+
+    pub fn as_pointer(&self) -> Option<Relocatable> {
+        match self {
+            Value::Pointer(ptr) => Some(*ptr),
+            Value::Felt(_) => None,
+        }
+    }
+}
+
+impl From<MaybeRelocatable> for Value {
+    fn from(value: MaybeRelocatable) -> Self {
+        match value {
+            MaybeRelocatable::Int(value) => Value::Felt(value),
+            MaybeRelocatable::RelocatableValue(ptr) => Value::Pointer(ptr),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CairoError {
+    Program(ProgramError),
+    Runner(RunnerError),
+    Run(Box<CairoRunError>),
+    Memory(MemoryError),
+    PublicInput(PublicInputError),
+    Io(io::Error),
+    EntrypointNotFound(String),
+    ContractNotFound(Felt252),
+    // invoke on a Cairo 1 contract or call on a Cairo 0 one
+    WrongContractKind(Felt252),
+    // the function takes an implicit argument whose builtin is not declared by the program
+    MissingBuiltin(String),
+    UnsupportedType(String),
+    WrongArgumentCount { entrypoint: String, expected: usize, given: usize },
+    // a Cairo 1 function panicked, with this data (usually a short string)
+    Panic(Vec<Felt252>),
+    // registry lookups (see registry.rs)
+    UnknownProgram(String),
+    WrongProgramKind(String),
+    UnsupportedCompiler { program: String, version: String },
+    ProgramHash { program: String, expected: Felt252, computed: Felt252 },
+    // a program of cairo_program/build/ that compile.sh didn't build
+    NotBuilt { program: String, path: PathBuf },
+}
+
+// 'Out of gas' for a short string, the number otherwise
+fn panic_message(felt: &Felt252) -> String {
+    let bytes = felt.to_biguint().to_bytes_be();
+    if !bytes.is_empty() && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        format!("'{}'", String::from_utf8_lossy(&bytes))
+    } else {
+        felt.to_string()
+    }
+}
+
+impl fmt::Display for CairoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CairoError::Program(e) => write!(f, "cannot load program: {}", e),
+            CairoError::Runner(e) => write!(f, "cannot initialize runner: {}", e),
+            CairoError::Run(e) => write!(f, "execution failed: {}", e),
+            CairoError::Memory(e) => write!(f, "memory error: {}", e),
+            CairoError::PublicInput(e) => write!(f, "cannot build the AIR public input: {}", e),
+            CairoError::Io(e) => write!(f, "io error: {}", e),
+            CairoError::EntrypointNotFound(name) => write!(f, "no function named {}", name),
+            CairoError::ContractNotFound(address) => write!(f, "no contract deployed at {}", address),
+            CairoError::WrongContractKind(address) => {
+                write!(f, "contract at {} is called the other way (invoke : Cairo 0, call : Cairo 1)", address)
+            }
+            CairoError::MissingBuiltin(name) => write!(f, "implicit argument {} has no builtin in this program", name),
+            CairoError::UnsupportedType(ty) => write!(f, "unsupported cairo type {}", ty),
+            CairoError::WrongArgumentCount { entrypoint, expected, given } => {
+                write!(f, "{} takes {} arguments, {} given", entrypoint, expected, given)
+            }
+            CairoError::Panic(data) => {
+                write!(f, "panicked with {}", data.iter().map(panic_message).collect::<Vec<_>>().join(", "))
+            }
+            CairoError::UnknownProgram(name) => write!(f, "no program named {} in the registry", name),
+            CairoError::WrongProgramKind(name) => write!(f, "{} is loaded the other way (executor : Cairo 0, contract : Cairo 1)", name),
+            CairoError::UnsupportedCompiler { program, version } => {
+                write!(f, "{} was compiled by an unsupported compiler version {}", program, version)
+            }
+            CairoError::ProgramHash { program, expected, computed } => {
+                write!(f, "{} hashes to {:#x}, the registry expects {:#x}", program, computed.to_biguint(), expected.to_biguint())
+            }
+            CairoError::NotBuilt { program, path } => {
+                write!(f, "{} is not built ({} is missing), run cairo_program/compile.sh", program, path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CairoError {}
+
+impl From<ProgramError> for CairoError {
+    fn from(e: ProgramError) -> Self {
+        CairoError::Program(e)
+    }
+}
+
+impl From<RunnerError> for CairoError {
+    fn from(e: RunnerError) -> Self {
+        CairoError::Runner(e)
+    }
+}
+
+impl From<CairoRunError> for CairoError {
+    fn from(e: CairoRunError) -> Self {
+        CairoError::Run(Box::new(e))
+    }
+}
+
+impl From<MemoryError> for CairoError {
+    fn from(e: MemoryError) -> Self {
+        CairoError::Memory(e)
+    }
+}
+
+impl From<PublicInputError> for CairoError {
+    fn from(e: PublicInputError) -> Self {
+        CairoError::PublicInput(e)
+    }
+}
+
+impl From<io::Error> for CairoError {
+    fn from(e: io::Error) -> Self {
+        CairoError::Io(e)
+    }
+}
+
+
+// == Entrypoints == //
+
+// Signature of a function, read from the program identifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entrypoint {
+    pub name: String,
+    pub pc: usize,
+    // implicit argument names, in order (e.g. ["output_ptr", "range_check_ptr"])
+    pub implicit_args: Vec<String>,
+    // number of explicit argument cells
+    pub args_size: usize,
+    // number of return value cells, implicit arguments excluded
+    pub return_size: usize,
+}
+
+// members of a struct identifier (Args, ImplicitArgs, user structs) sorted by offset
+fn members(program: &Program, path: &str) -> Vec<(String, String)> {
+    let mut members = program
+        .get_identifier(path)
+        .and_then(|id| id.members.as_ref())
+        .map(|m| m.iter().map(|(name, m)| (m.offset, name.clone(), m.cairo_type.clone())).collect::<Vec<_>>())
+        .unwrap_or_default();
+    members.sort();
+    members.into_iter().map(|(_, name, ty)| (name, ty)).collect()
+}
+
+// number of cells taken by a value of type 'ty'
+fn type_size(program: &Program, ty: &str) -> Result<usize, CairoError> {
+    let ty = ty.trim();
+    if ty == "felt" || ty.ends_with('*') {
+        return Ok(1);
+    }
+    if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        return split_tuple(inner)
+            .iter()
+            .map(|member| {
+                // named tuple member "name: type"
+                let member_ty = member.split_once(':').map(|(_, t)| t).unwrap_or(member);
+                type_size(program, member_ty)
+            })
+            .sum();
+    }
+    let members = program
+        .get_identifier(ty)
+        .and_then(|id| id.members.as_ref())
+        .ok_or_else(|| CairoError::UnsupportedType(ty.to_string()))?;
+    members.values().map(|m| type_size(program, &m.cairo_type)).sum()
+}
+
+// top level members of a tuple type, "a: felt, b: (felt, felt)" -> ["a: felt", "b: (felt, felt)"]
+fn split_tuple(inner: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
+
+
+// == Executor == //
+
+pub struct CairoExecutor {
+    program: Program,
+    layout: Layout,
+}
+
+// A finished execution, keeps the VM to read memory afterwards
+pub struct CairoRun {
+    pub runner: CairoRunner,
+    pub vm: VirtualMachine,
+    return_values: Vec<Value>,
+}
+
+impl CairoExecutor {
+    pub fn new(program: Program) -> Self {
+        Self { program, layout: Layout::AllCairo }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, CairoError> {
+        Ok(Self::new(Program::from_file(path, None)?))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CairoError> {
+        Ok(Self::new(Program::from_bytes(bytes, None)?))
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    // 'name' is either a function of the main module or a fully qualified path
+    pub fn entrypoint(&self, name: &str) -> Result<Entrypoint, CairoError> {
+        let full_name = if name.contains('.') { name.to_string() } else { format!("__main__.{}", name) };
+        let pc = self
+            .program
+            .get_identifier(&full_name)
+            .filter(|id| id.type_.as_deref() == Some("function"))
+            .and_then(|id| id.pc)
+            .ok_or_else(|| CairoError::EntrypointNotFound(name.to_string()))?;
+
+        let implicit_args = members(&self.program, &format!("{}.ImplicitArgs", full_name))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let args_size = members(&self.program, &format!("{}.Args", full_name))
+            .iter()
+            .map(|(_, ty)| type_size(&self.program, ty))
+            .sum::<Result<usize, _>>()?;
+        let return_size = match self
+            .program
+            .get_identifier(&format!("{}.Return", full_name))
+            .and_then(|id| id.cairo_type.clone())
+        {
+            Some(ty) => type_size(&self.program, &ty)?,
+            None => 0,
+        };
+
+        Ok(Entrypoint { name: full_name, pc, implicit_args, args_size, return_size })
+    }
+
+    pub fn run(&self, name: &str, args: &[Argument]) -> Result<CairoRun, CairoError> {
+        self.run_with_hints(name, args, &mut BuiltinHintProcessor::new_empty())
+    }
+
+    pub fn run_with_hints(
+        &self,
+        name: &str,
+        args: &[Argument],
+        hint_processor: &mut dyn HintProcessor,
+    ) -> Result<CairoRun, CairoError> {
+        let mut vm = VirtualMachine::new(false);
+        let mut runner = CairoRunner::new(&self.program, self.layout.as_str(), false)?;
+        let return_values = self.execute(name, args, &mut runner, &mut vm, hint_processor)?;
+        Ok(CairoRun { runner, vm, return_values })
+    }
+
+    // runs 'name' on a fresh runner, the VM is left as it was if the run fails (see trace.rs)
+    pub(crate) fn execute(
+        &self,
+        name: &str,
+        args: &[Argument],
+        runner: &mut CairoRunner,
+        vm: &mut VirtualMachine,
+        hint_processor: &mut dyn HintProcessor,
+    ) -> Result<Vec<Value>, CairoError> {
+        let entrypoint = self.entrypoint(name)?;
+        if args.len() != entrypoint.args_size {
+            return Err(CairoError::WrongArgumentCount {
+                entrypoint: entrypoint.name,
+                expected: entrypoint.args_size,
+                given: args.len(),
+            });
+        }
+
+        runner.initialize_builtins(vm)?;
+        runner.initialize_segments(vm, None);
+
+        // implicit arguments first, bound to the builtin segments
+        let mut cairo_args = vec![];
+        for arg in &entrypoint.implicit_args {
+            // StarkNet contracts : syscalls are written to a fresh segment and served by the hint
+            // processor (see starknet.rs)
+            if arg == "syscall_ptr" {
+                cairo_args.push(CairoArg::Single(vm.add_memory_segment().into()));
+                continue;
+            }
+            let builtin = format!("{}_builtin", arg.strip_suffix("_ptr").unwrap_or(arg));
+            let base = vm
+                .get_builtin_runners()
+                .iter()
+                .find(|runner| runner.name() == builtin)
+                .map(|runner| runner.base())
+                .ok_or_else(|| CairoError::MissingBuiltin(arg.clone()))?;
+            cairo_args.push(CairoArg::Single(Relocatable::from((base as isize, 0)).into()));
+        }
+        cairo_args.extend(args.iter().map(Argument::to_cairo_arg));
+        let cairo_args = cairo_args.iter().collect::<Vec<_>>();
+
+        runner.run_from_entrypoint(entrypoint.pc, &cairo_args, false, None, vm, hint_processor)?;
+
+        let values = vm.get_return_values(entrypoint.implicit_args.len() + entrypoint.return_size)?;
+        Ok(values.into_iter().skip(entrypoint.implicit_args.len()).map(Value::from).collect())
+    }
+}
+
+impl CairoRun {
+    pub fn return_values(&self) -> &[Value] {
+        &self.return_values
+    }
+
+    pub fn read_felts(&self, ptr: Relocatable, len: usize) -> Result<Vec<Felt252>, CairoError> {
+        (0..len)
+            .map(|i| Ok(self.vm.get_integer(Relocatable::from((ptr.segment_index, ptr.offset + i)))?.into_owned()))
+            .collect()
+    }
+
+    // contents of the output builtin segment, empty if the program has no output builtin
+    pub fn output(&self) -> Result<Vec<Felt252>, CairoError> {
+        let base = self.vm.get_builtin_runners().iter().find_map(|runner| match runner {
+            BuiltinRunner::Output(output) if runner.name() == OUTPUT_BUILTIN_NAME => Some(output.base()),
+            _ => None,
+        });
+        let base = match base {
+            Some(base) => base,
+            None => return Ok(vec![]),
+        };
+        // segment sizes are computed at the end of the run
+        let size = self.vm.get_segment_used_size(base).unwrap_or(0);
+        self.read_felts(Relocatable::from((base as isize, 0)), size)
+    }
+}
+
+
+#[cfg(test)]
+fn abs_value_array() -> CairoExecutor {
+    // cairo-compile cairo_program/abs_value_array.cairo --output cairo_program/abs_value_array.json
+    registry::executor("abs_value_array").unwrap()
+}
+
+#[test]
+pub fn test_run_cairo() {
+    let executor = abs_value_array();
+    let entrypoint = executor.entrypoint("compare_abs_arrays").unwrap();
+    assert_eq!(entrypoint.implicit_args, vec!["range_check_ptr"]);
+    assert_eq!((entrypoint.args_size, entrypoint.return_size), (4, 1));
+
+    let args = |b: i64| [Argument::array([-7i64, -10]), Argument::array([7, b]), Argument::felt(2), Argument::felt(0)];
+    let run = executor.run("compare_abs_arrays", &args(10)).unwrap();
+    assert_eq!(run.return_values(), &[Value::Felt(Felt252::from(1))]);
+    let run = executor.run("compare_abs_arrays", &args(11)).unwrap();
+    assert_eq!(run.return_values(), &[Value::Felt(Felt252::from(0))]);
+
+    let run = executor.run("main", &[]).unwrap();
+    assert!(run.return_values().is_empty());
+    assert!(run.output().unwrap().is_empty());
+}
+
+#[test]
+pub fn test_run_cairo_errors() {
+    let executor = abs_value_array();
+    assert!(matches!(executor.run("missing", &[]), Err(CairoError::EntrypointNotFound(_))));
+    assert!(matches!(executor.run("main", &[Argument::felt(1)]), Err(CairoError::WrongArgumentCount { .. })));
+
+    // fill_array asserts array[0] = base, 1 != 0
+    let args = [Argument::array([1i64, 2]), Argument::felt(0), Argument::felt(1), Argument::felt(2), Argument::felt(0)];
+    assert!(matches!(executor.run("fill_array", &args), Err(CairoError::Run(_))));
+
+    // the plain layout has no range check builtin
+    let executor = executor.with_layout(Layout::Plain);
+    assert!(matches!(executor.run("main", &[]), Err(CairoError::Runner(_))));
+}
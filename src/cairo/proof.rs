@@ -0,0 +1,230 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::BuiltinHintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessor;
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::vm_exception::VmException;
+use cairo_vm::vm::runners::cairo_runner::CairoRunner;
+use cairo_vm::vm::trace::trace_entry::TraceEntry;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use serde_json::{json, Map, Value as Json};
+
+use super::{CairoError, CairoExecutor, CairoRun};
+
+// Proof mode : what a STARK prover (stone, ...) needs to prove a run.
+//   let proof = CairoExecutor::from_file(path)?.with_layout(Layout::Small).run_proof_mode()?;
+//   proof.export(dir)?;   // dir/trace.bin, dir/memory.bin, dir/air_public_input.json, dir/air_private_input.json
+// Same as `cairo-run --proof_mode --trace_file trace.bin --memory_file memory.bin
+// --air_public_input air_public_input.json --air_private_input air_private_input.json`.
+// The program must be compiled with `cairo-compile --proof_mode` : execution starts at __start__,
+// calls main and loops on __end__ until the trace is padded for the layout.
+
+pub const TRACE_FILE: &str = "trace.bin";
+pub const MEMORY_FILE: &str = "memory.bin";
+pub const AIR_PUBLIC_INPUT_FILE: &str = "air_public_input.json";
+pub const AIR_PRIVATE_INPUT_FILE: &str = "air_private_input.json";
+
+// A relocated proof mode run
+pub struct ProofRun {
+    pub run: CairoRun,
+}
+
+// Paths of the exported files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofFiles {
+    pub trace: PathBuf,
+    pub memory: PathBuf,
+    pub air_public_input: PathBuf,
+    pub air_private_input: PathBuf,
+}
+
+impl CairoExecutor {
+    pub fn run_proof_mode(&self) -> Result<ProofRun, CairoError> {
+        self.run_proof_mode_with_hints(&mut BuiltinHintProcessor::new_empty())
+    }
+
+    // the steps of cairo_run::cairo_run with proof_mode = true
+    pub fn run_proof_mode_with_hints(&self, hint_processor: &mut dyn HintProcessor) -> Result<ProofRun, CairoError> {
+        let mut vm = VirtualMachine::new(true);
+        let mut runner = CairoRunner::new(&self.program, self.layout.as_str(), true)?;
+        let end = runner.initialize(&mut vm)?;
+
+        runner
+            .run_until_pc(end, &mut vm, hint_processor)
+            .map_err(|e| CairoRunError::from(VmException::from_vm_error(&runner, &vm, e)))?;
+        // pads the trace up to a power of 2 with enough steps for the builtins used
+        runner.end_run(false, false, &mut vm, hint_processor).map_err(CairoRunError::from)?;
+        vm.verify_auto_deductions().map_err(CairoRunError::from)?;
+        runner.read_return_values(&mut vm)?;
+        runner.finalize_segments(&mut vm)?;
+        runner.relocate(&mut vm, true).map_err(CairoRunError::from)?;
+
+        Ok(ProofRun { run: CairoRun { runner, vm, return_values: vec![] } })
+    }
+}
+
+impl ProofRun {
+    // relocated (pc, ap, fp) of every step, padding included
+    pub fn trace(&self) -> &[TraceEntry] {
+        // relocate() was called with a traced VM
+        self.run.vm.get_relocated_trace().unwrap()
+    }
+
+    // trace.bin : (ap, fp, pc) of every step as little-endian u64
+    pub fn encoded_trace(&self) -> Vec<u8> {
+        self.trace()
+            .iter()
+            .flat_map(|entry| [entry.ap, entry.fp, entry.pc])
+            .flat_map(|value| (value as u64).to_le_bytes())
+            .collect()
+    }
+
+    // memory.bin : (address as little-endian u64, value as 32 little-endian bytes) of every written cell
+    pub fn encoded_memory(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (address, value) in self.run.runner.relocated_memory.iter().enumerate() {
+            if let Some(value) = value {
+                bytes.extend((address as u64).to_le_bytes());
+                bytes.extend(felt_to_le_bytes(value));
+            }
+        }
+        bytes
+    }
+
+    pub fn air_public_input(&self) -> Result<String, CairoError> {
+        Ok(self.run.runner.get_air_public_input(&self.run.vm)?.serialize_json()?)
+    }
+
+    // inputs of every builtin instance, with the paths of the trace and memory files
+    pub fn air_private_input(&self, trace_path: &Path, memory_path: &Path) -> Result<Json, CairoError> {
+        let mut private_input = Map::new();
+        private_input.insert("trace_path".to_string(), json!(trace_path));
+        private_input.insert("memory_path".to_string(), json!(memory_path));
+        for builtin in self.run.vm.get_builtin_runners() {
+            let name = builtin.name().strip_suffix("_builtin").unwrap_or(builtin.name());
+            if let Some(instances) = self.builtin_inputs(name, builtin.base())? {
+                private_input.insert(name.to_string(), Json::Array(instances));
+            }
+        }
+        Ok(Json::Object(private_input))
+    }
+
+    // None for the builtins without private input (output) or not supported here (ecdsa, the
+    // signatures are kept by the hints of the run)
+    fn builtin_inputs(&self, name: &str, base: usize) -> Result<Option<Vec<Json>>, CairoError> {
+        let (cells_per_instance, inputs): (usize, &[&str]) = match name {
+            "range_check" => (1, &["value"]),
+            "pedersen" => (3, &["x", "y"]),
+            "bitwise" => (5, &["x", "y"]),
+            "ec_op" => (7, &["p_x", "p_y", "q_x", "q_y", "m"]),
+            "poseidon" => (6, &["input_s0", "input_s1", "input_s2"]),
+            "keccak" => (16, &["input_s0", "input_s1", "input_s2", "input_s3", "input_s4", "input_s5", "input_s6", "input_s7"]),
+            _ => return Ok(None),
+        };
+        let used = self.run.vm.get_segment_used_size(base).unwrap_or(0);
+        let mut instances = vec![];
+        for index in 0..used.div_ceil(cells_per_instance) {
+            let mut instance = Map::new();
+            instance.insert("index".to_string(), json!(index));
+            for (i, input) in inputs.iter().enumerate() {
+                let address = Relocatable::from((base as isize, index * cells_per_instance + i));
+                // instances the program did not fill are left to the prover
+                if let Ok(value) = self.run.vm.get_integer(address) {
+                    instance.insert(input.to_string(), json!(format!("{:#x}", value.to_biguint())));
+                }
+            }
+            if instance.len() == inputs.len() + 1 {
+                instances.push(Json::Object(instance));
+            }
+        }
+        Ok(Some(instances))
+    }
+
+    // writes the four files in 'dir' under their cairo-run names
+    pub fn export(&self, dir: &Path) -> Result<ProofFiles, CairoError> {
+        fs::create_dir_all(dir)?;
+        let files = ProofFiles {
+            trace: dir.join(TRACE_FILE),
+            memory: dir.join(MEMORY_FILE),
+            air_public_input: dir.join(AIR_PUBLIC_INPUT_FILE),
+            air_private_input: dir.join(AIR_PRIVATE_INPUT_FILE),
+        };
+        fs::write(&files.trace, self.encoded_trace())?;
+        fs::write(&files.memory, self.encoded_memory())?;
+        fs::write(&files.air_public_input, self.air_public_input()?)?;
+        // the prover reads the trace and memory through these paths
+        let (trace, memory) = (fs::canonicalize(&files.trace)?, fs::canonicalize(&files.memory)?);
+        let private_input = serde_json::to_string_pretty(&self.air_private_input(&trace, &memory)?).unwrap();
+        fs::write(&files.air_private_input, private_input)?;
+        Ok(files)
+    }
+}
+
+fn felt_to_le_bytes(value: &Felt252) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    let le = value.to_biguint().to_bytes_le();
+    bytes[..le.len()].copy_from_slice(&le);
+    bytes
+}
+
+
+// The tests need abs_value_array.cairo compiled with --proof_mode, which compile.sh builds :
+//   cairo_program/compile.sh && cargo test proof_mode -- --ignored
+#[cfg(test)]
+fn abs_value_array_proof() -> CairoExecutor {
+    super::registry::executor("abs_value_array_proof").unwrap()
+}
+
+#[test]
+#[ignore]
+pub fn test_proof_mode_run() {
+    use super::Layout;
+
+    let proof = abs_value_array_proof().with_layout(Layout::Small).run_proof_mode().unwrap();
+    let trace = proof.trace();
+    assert!(trace.len().is_power_of_two());
+    // relocated addresses start at 1 : __start__ is pc 1, the padding loops on __end__
+    assert_eq!(trace[0].pc, 1);
+    assert_eq!(trace.last().unwrap().pc, 5);
+
+    assert_eq!(proof.encoded_trace().len(), 24 * trace.len());
+    let cells = proof.run.runner.relocated_memory.iter().filter(|cell| cell.is_some()).count();
+    assert_eq!(proof.encoded_memory().len(), 40 * cells);
+    assert_eq!(&proof.encoded_trace()[..8], &(trace[0].ap as u64).to_le_bytes());
+
+    // a program compiled without --proof_mode has no __start__
+    let executor = super::abs_value_array().with_layout(Layout::Small);
+    assert!(matches!(executor.run_proof_mode(), Err(CairoError::Runner(_))));
+}
+
+#[test]
+#[ignore]
+pub fn test_proof_mode_export() {
+    use super::Layout;
+
+    let proof = abs_value_array_proof().with_layout(Layout::Small).run_proof_mode().unwrap();
+    let dir = std::env::temp_dir().join(format!("finality_sequencer_proof_{}", std::process::id()));
+    let files = proof.export(&dir).unwrap();
+    assert_eq!(files.trace, dir.join("trace.bin"));
+    assert_eq!(fs::read(&files.trace).unwrap(), proof.encoded_trace());
+    assert_eq!(fs::read(&files.memory).unwrap(), proof.encoded_memory());
+
+    let public_input: Json = serde_json::from_slice(&fs::read(&files.air_public_input).unwrap()).unwrap();
+    assert_eq!(public_input["layout"], "small");
+    assert_eq!(public_input["n_steps"], proof.trace().len());
+    assert!(public_input["memory_segments"]["range_check"].is_object());
+
+    // 2 abs_value per element of the 10-element arrays, each checked with range checks
+    let private_input: Json = serde_json::from_slice(&fs::read(&files.air_private_input).unwrap()).unwrap();
+    assert_eq!(private_input["trace_path"], json!(fs::canonicalize(&files.trace).unwrap()));
+    let range_checks = private_input["range_check"].as_array().unwrap();
+    assert!(range_checks.len() >= 20);
+    assert_eq!(range_checks[0]["index"], 0);
+    assert!(range_checks[0]["value"].as_str().unwrap().starts_with("0x"));
+    assert_eq!(private_input["pedersen"], json!([]));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,279 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cairo_lang_starknet::casm_contract_class::{CasmContractClass, CasmContractEntryPoint};
+use cairo_vm::felt::Felt252;
+use cairo_vm::types::errors::program_errors::ProgramError;
+use cairo_vm::types::program::Program;
+use cairo_vm::types::relocatable::MaybeRelocatable;
+use serde::Deserialize;
+use starknet_crypto::{pedersen_hash, poseidon_hash_many, FieldElement};
+
+use super::cairo1::CasmContract;
+use super::dispute::to_felt;
+use super::{CairoError, CairoExecutor};
+
+// The compiled programs of cairo_program/, looked up by name :
+//   let executor = registry::executor("abs_value_array")?;
+//   let contract = registry::contract("dispute_contract_cairo1")?;
+// Compiler output committed in cairo_program/ is embedded in the binary, the other programs are
+// built by cairo_program/compile.sh into cairo_program/build/ and read when they are loaded.
+// Loading checks the compiler version, and for an embedded program recomputes its hash the way
+// Cairo does (program hash of a Cairo 0 program, compiled class hash of a Cairo 1 contract)
+// against the one recorded here, so a recompiled or edited artifact fails until its entry is updated.
+
+// == Artifacts == //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    // a program of cairo-compile
+    Cairo0,
+    // a contract class of starknet-sierra-compile
+    Cairo1,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    // committed in cairo_program/
+    Embedded(&'static [u8]),
+    // built in cairo_program/build/ by compile.sh
+    Build,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Artifact {
+    pub name: &'static str,
+    pub kind: Kind,
+    // relative to cairo_program/ or cairo_program/build/
+    pub path: &'static str,
+    pub source: Source,
+    // program hash or compiled class hash, as 0x hex. None for a built program, whose output
+    // depends on the local compiler
+    pub hash: Option<&'static str>,
+}
+
+macro_rules! artifact {
+    ($name:expr, $kind:expr, $path:expr, $hash:expr) => {
+        Artifact {
+            name: $name,
+            kind: $kind,
+            path: $path,
+            source: Source::Embedded(include_bytes!(concat!("../../cairo_program/", $path))),
+            hash: Some($hash),
+        }
+    };
+    ($name:expr, $kind:expr, $path:expr) => {
+        Artifact { name: $name, kind: $kind, path: $path, source: Source::Build, hash: None }
+    };
+}
+
+pub const ARTIFACTS: &[Artifact] = &[
+    artifact!("abs_value_array", Kind::Cairo0, "abs_value_array.json", "0x063ad93a30af369556ada542d2db7e0c2312e2038da6aee3e75fb70d877caa99"),
+    artifact!("abs_value_array_proof", Kind::Cairo0, "abs_value_array_proof.json"),
+    artifact!("dispute", Kind::Cairo0, "dispute.json"),
+    artifact!("dispute_contract", Kind::Cairo0, "dispute_contract.json"),
+    artifact!("sequencer_hints", Kind::Cairo0, "sequencer_hints.json"),
+    artifact!("dispute_contract_cairo1", Kind::Cairo1, "dispute_contract.compiled_contract_class.json"),
+];
+
+pub fn artifact(name: &str) -> Result<&'static Artifact, CairoError> {
+    ARTIFACTS.iter().find(|artifact| artifact.name == name).ok_or_else(|| CairoError::UnknownProgram(name.to_string()))
+}
+
+pub fn executor(name: &str) -> Result<CairoExecutor, CairoError> {
+    artifact(name)?.executor()
+}
+
+pub fn contract(name: &str) -> Result<CasmContract, CairoError> {
+    artifact(name)?.contract()
+}
+
+pub fn build_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("cairo_program").join("build")
+}
+
+#[derive(Deserialize)]
+struct Header {
+    compiler_version: String,
+}
+
+impl Artifact {
+    // the compiler output, NotBuilt if compile.sh wasn't run
+    pub fn bytes(&self) -> Result<Cow<'static, [u8]>, CairoError> {
+        match self.source {
+            Source::Embedded(bytes) => Ok(Cow::Borrowed(bytes)),
+            Source::Build => {
+                let path = build_dir().join(self.path);
+                fs::read(&path).map(Cow::Owned).map_err(|e| match e.kind() {
+                    io::ErrorKind::NotFound => CairoError::NotBuilt { program: self.name.to_string(), path },
+                    _ => CairoError::Io(e),
+                })
+            }
+        }
+    }
+
+    pub fn executor(&self) -> Result<CairoExecutor, CairoError> {
+        let bytes = self.load(Kind::Cairo0)?;
+        let executor = CairoExecutor::from_bytes(&bytes)?;
+        self.check_hash(|| program_hash(executor.program()))?;
+        Ok(executor)
+    }
+
+    pub fn contract(&self) -> Result<CasmContract, CairoError> {
+        let bytes = self.load(Kind::Cairo1)?;
+        let contract = CasmContract::from_bytes(&bytes)?;
+        self.check_hash(|| Ok(compiled_class_hash(contract.class())))?;
+        Ok(contract)
+    }
+
+    // what 'hash' has to be for the current output, to pin a program once compile.sh built it
+    pub fn computed_hash(&self) -> Result<Felt252, CairoError> {
+        match self.kind {
+            Kind::Cairo0 => program_hash(CairoExecutor::from_bytes(&self.load(Kind::Cairo0)?)?.program()),
+            Kind::Cairo1 => Ok(compiled_class_hash(CasmContract::from_bytes(&self.load(Kind::Cairo1)?)?.class())),
+        }
+    }
+
+    // the bytes of an artifact of this kind, from a supported compiler
+    fn load(&self, kind: Kind) -> Result<Cow<'static, [u8]>, CairoError> {
+        if self.kind != kind {
+            return Err(CairoError::WrongProgramKind(self.name.to_string()));
+        }
+        let bytes = self.bytes()?;
+        let header: Header = serde_json::from_slice(&bytes).map_err(ProgramError::from)?;
+        if !is_supported(kind, &header.compiler_version) {
+            return Err(CairoError::UnsupportedCompiler { program: self.name.to_string(), version: header.compiler_version });
+        }
+        Ok(bytes)
+    }
+
+    fn check_hash(&self, computed: impl FnOnce() -> Result<Felt252, CairoError>) -> Result<(), CairoError> {
+        let expected = match self.hash {
+            Some(hash) => Felt252::parse_bytes(hash.trim_start_matches("0x").as_bytes(), 16).unwrap_or_default(),
+            None => return Ok(()),
+        };
+        let computed = computed()?;
+        if computed != expected {
+            return Err(CairoError::ProgramHash { program: self.name.to_string(), expected, computed });
+        }
+        Ok(())
+    }
+}
+
+// One line per artifact, its name and computed hash as ARTIFACTS records it (or why it has none) :
+//   cairo_program/compile.sh && cargo run --release -- hashes
+pub fn hashes() -> String {
+    ARTIFACTS
+        .iter()
+        .map(|artifact| match artifact.computed_hash() {
+            Ok(hash) => format!("{} {:#066x}", artifact.name, hash.to_biguint()),
+            Err(e) => format!("{} {}", artifact.name, e),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// What cairo-vm 0.9 runs : cairo-compile 0.10 to 0.13, and the 2.3 Sierra compilers, the minor
+// of the cairo-lang crates the class is parsed and hashed with (later classes have a segmented
+// bytecode, which compiled_class_hash doesn't handle)
+pub fn is_supported(kind: Kind, version: &str) -> bool {
+    let mut parts = version.split('.').map(|part| part.parse::<u64>());
+    match (kind, parts.next(), parts.next()) {
+        (Kind::Cairo0, Some(Ok(0)), Some(Ok(minor))) => (10..=13).contains(&minor),
+        (Kind::Cairo1, Some(Ok(2)), Some(Ok(3))) => true,
+        _ => false,
+    }
+}
+
+
+// == Hashes == //
+
+fn field(felt: &Felt252) -> FieldElement {
+    FieldElement::from_bytes_be(&felt.to_be_bytes()).unwrap()
+}
+
+fn ascii(s: &str) -> FieldElement {
+    FieldElement::from_byte_slice_be(s.as_bytes()).unwrap()
+}
+
+// h(d[0], h(d[1], ... h(d[n-2], d[n-1])))
+fn hash_chain(data: &[FieldElement]) -> FieldElement {
+    data.iter().rev().copied().reduce(|acc, x| pedersen_hash(&x, &acc)).unwrap_or(FieldElement::ZERO)
+}
+
+// compute_program_hash_chain of cairo-lang (bootloader version 0) : the hash chain of
+// [bootloader version, main, builtin count, builtins..., data...] prefixed by its length.
+// Programs without main (dispute.json, dispute_contract.json) are hashed with main = 0.
+pub fn program_hash(program: &Program) -> Result<Felt252, CairoError> {
+    let main = program.get_identifier("__main__.main").and_then(|main| main.pc).unwrap_or(0);
+    let mut chain = vec![FieldElement::ZERO, FieldElement::from(main as u64), FieldElement::from(program.builtins_len() as u64)];
+    chain.extend(program.iter_builtins().map(|builtin| ascii(builtin.name().trim_end_matches("_builtin"))));
+    for value in program.iter_data() {
+        match value {
+            MaybeRelocatable::Int(value) => chain.push(field(value)),
+            MaybeRelocatable::RelocatableValue(_) => return Err(CairoError::UnsupportedType("relocatable in program data".to_string())),
+        }
+    }
+    chain.insert(0, FieldElement::from(chain.len() as u64));
+    Ok(to_felt(&hash_chain(&chain)))
+}
+
+fn entrypoints_hash(entrypoints: &[CasmContractEntryPoint]) -> FieldElement {
+    let mut data = vec![];
+    for entrypoint in entrypoints {
+        data.push(field(&Felt252::from(entrypoint.selector.clone())));
+        data.push(FieldElement::from(entrypoint.offset as u64));
+        data.push(poseidon_hash_many(&entrypoint.builtins.iter().map(|builtin| ascii(builtin)).collect::<Vec<_>>()));
+    }
+    poseidon_hash_many(&data)
+}
+
+// The class hash StarkNet declares a Cairo 1 contract with. A 2.3 class has no
+// bytecode_segment_lengths, its bytecode is hashed in one piece.
+pub fn compiled_class_hash(class: &CasmContractClass) -> Felt252 {
+    let entrypoints = &class.entry_points_by_type;
+    let bytecode = class.bytecode.iter().map(|value| field(&Felt252::from(value.value.clone()))).collect::<Vec<_>>();
+    to_felt(&poseidon_hash_many(&[
+        ascii("COMPILED_CLASS_V1"),
+        entrypoints_hash(&entrypoints.external),
+        entrypoints_hash(&entrypoints.l1_handler),
+        entrypoints_hash(&entrypoints.constructor),
+        poseidon_hash_many(&bytecode),
+    ]))
+}
+
+
+#[test]
+pub fn test_registry() {
+    // built programs load once compile.sh has run
+    for artifact in ARTIFACTS {
+        let loaded = match artifact.kind {
+            Kind::Cairo0 => artifact.executor().map(drop),
+            Kind::Cairo1 => artifact.contract().map(drop),
+        };
+        match artifact.source {
+            Source::Embedded(_) => loaded.unwrap(),
+            Source::Build => assert!(matches!(loaded, Ok(()) | Err(CairoError::NotBuilt { .. }))),
+        }
+    }
+    assert!(matches!(executor("missing"), Err(CairoError::UnknownProgram(_))));
+    assert!(matches!(contract("dispute_contract"), Err(CairoError::WrongProgramKind(_))));
+
+    // the hash to pin is the one loading checks
+    let pinned = artifact("abs_value_array").unwrap();
+    assert_eq!(format!("{:#066x}", pinned.computed_hash().unwrap().to_biguint()), pinned.hash.unwrap());
+    assert!(hashes().lines().any(|line| line == format!("abs_value_array {}", pinned.hash.unwrap())));
+
+    // an artifact that doesn't match its recorded hash
+    let tampered = Artifact { hash: Some("0x1"), ..*artifact("abs_value_array").unwrap() };
+    let err = tampered.executor().err().unwrap();
+    assert!(matches!(err, CairoError::ProgramHash { ref expected, .. } if *expected == Felt252::from(1)));
+
+    assert!(is_supported(Kind::Cairo0, "0.10.3"));
+    assert!(!is_supported(Kind::Cairo0, "0.9.1"));
+    assert!(is_supported(Kind::Cairo1, "2.3.1"));
+    assert!(!is_supported(Kind::Cairo1, "2.6.4"));
+    assert!(!is_supported(Kind::Cairo1, "1.1.0"));
+}
@@ -0,0 +1,616 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use cairo_lang_casm::hints::{Hint, StarknetHint};
+use cairo_lang_casm::operand::{BinOpOperand, CellRef, DerefOrImmediate, Operation, Register, ResOperand};
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::{BuiltinHintProcessor, HintProcessorData};
+use cairo_vm::hint_processor::builtin_hint_processor::hint_utils::get_ptr_from_var_name;
+use cairo_vm::hint_processor::cairo_1_hint_processor::hint_processor::Cairo1HintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::{HintProcessorLogic, HintReference};
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
+use cairo_vm::vm::runners::cairo_runner::{ResourceTracker, RunResources};
+use cairo_vm::vm::vm_core::VirtualMachine;
+use num_bigint::BigUint;
+
+use super::cairo1::{sn_keccak, CasmContract};
+use super::{Argument, CairoError, CairoExecutor, CairoRun, Value};
+
+// A StarkNet-like environment in process, to run contracts end to end in tests.
+//   let mut state = StarknetState::default();
+//   let contract = state.deploy(CairoExecutor::from_file(path)?);
+//   let call = state.invoke(&sequencer, &contract, "post_root", &[Argument::felt(batch), Argument::Felt(root)])?;
+//   call.retdata, call.events, state.storage(&contract, &short_string("slashed"))
+// Cairo 0 contracts use starkware.starknet.common.syscalls : storage_read, storage_write,
+// emit_event and get_caller_address are served by the hints of SyscallHandler. Their functions
+// are called by name with their explicit arguments (no __wrappers__ / calldata).
+// Cairo 1 contracts (CasmContract) are called with calldata instead :
+//   let contract = state.deploy(CasmContract::from_file(path)?);
+//   let call = state.call(&caller, &contract, "dispute", &claim.to_felts())?;
+// and get storage_read, storage_write, emit_event and get_execution_info. An invoke or a call
+// that fails leaves the storage and the events as they were.
+
+// == State == //
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub from_address: Felt252,
+    pub keys: Vec<Felt252>,
+    pub data: Vec<Felt252>,
+}
+
+// What an invoke returned and emitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInfo {
+    pub retdata: Vec<Felt252>,
+    pub events: Vec<Event>,
+}
+
+// A deployed contract, see deploy
+pub enum Contract {
+    Cairo0(CairoExecutor),
+    Cairo1(Box<CasmContract>),
+}
+
+impl From<CairoExecutor> for Contract {
+    fn from(executor: CairoExecutor) -> Self {
+        Contract::Cairo0(executor)
+    }
+}
+
+impl From<CasmContract> for Contract {
+    fn from(contract: CasmContract) -> Self {
+        Contract::Cairo1(Box::new(contract))
+    }
+}
+
+#[derive(Default)]
+pub struct StarknetState {
+    contracts: HashMap<Felt252, Contract>,
+    // (contract, storage address) -> value, 0 when never written
+    storage: HashMap<(Felt252, Felt252), Felt252>,
+    events: Vec<Event>,
+}
+
+// Cairo's short string literal : 'abc' is the felt of the big-endian bytes of "abc"
+pub fn short_string(s: &str) -> Felt252 {
+    assert!(s.len() <= 31, "short strings hold at most 31 characters");
+    Felt252::from(BigUint::from_bytes_be(s.as_bytes()))
+}
+
+impl StarknetState {
+    // contracts get the addresses 1, 2, 3, ...
+    pub fn deploy(&mut self, contract: impl Into<Contract>) -> Felt252 {
+        let address = Felt252::from(self.contracts.len() as u64 + 1);
+        self.contracts.insert(address.clone(), contract.into());
+        address
+    }
+
+    pub fn storage(&self, contract: &Felt252, address: &Felt252) -> Felt252 {
+        self.storage.get(&(contract.clone(), address.clone())).cloned().unwrap_or_else(|| Felt252::from(0))
+    }
+
+    // every event emitted so far, in order
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    // calls a function of a Cairo 0 contract
+    pub fn invoke(&mut self, caller: &Felt252, contract: &Felt252, function: &str, args: &[Argument]) -> Result<CallInfo, CairoError> {
+        let executor = match self.contracts.get(contract) {
+            Some(Contract::Cairo0(executor)) => executor,
+            Some(Contract::Cairo1(_)) => return Err(CairoError::WrongContractKind(contract.clone())),
+            None => return Err(CairoError::ContractNotFound(contract.clone())),
+        };
+        let hints = Hints::Cairo0(BuiltinHintProcessor::new_empty());
+        let mut handler = SyscallHandler::new(self, contract.clone(), caller.clone(), sn_keccak(function), hints);
+        let run = executor.run_with_hints(function, args, &mut handler)?;
+        let SyscallHandler { writes, events, .. } = handler;
+        self.commit(contract, &run, writes, events)
+    }
+
+    // calls an external function of a Cairo 1 contract
+    pub fn call(&mut self, caller: &Felt252, contract: &Felt252, function: &str, calldata: &[Felt252]) -> Result<CallInfo, CairoError> {
+        let casm = match self.contracts.get(contract) {
+            Some(Contract::Cairo1(casm)) => casm,
+            Some(Contract::Cairo0(_)) => return Err(CairoError::WrongContractKind(contract.clone())),
+            None => return Err(CairoError::ContractNotFound(contract.clone())),
+        };
+        let hints = Hints::Cairo1(Cairo1HintProcessor::new(casm.hints(), RunResources::default()));
+        let mut handler = SyscallHandler::new(self, contract.clone(), caller.clone(), sn_keccak(function), hints);
+        let run = casm.run_with_hints(function, calldata, &mut handler)?;
+        let SyscallHandler { writes, events, .. } = handler;
+        self.commit(contract, &run, writes, events)
+    }
+
+    // applies the writes and the events of a successful run
+    fn commit(
+        &mut self,
+        contract: &Felt252,
+        run: &CairoRun,
+        writes: HashMap<Felt252, Felt252>,
+        events: Vec<Event>,
+    ) -> Result<CallInfo, CairoError> {
+        let retdata = run
+            .return_values()
+            .iter()
+            .map(|value| match value {
+                Value::Felt(felt) => Ok(felt.clone()),
+                Value::Pointer(_) => Err(CairoError::UnsupportedType("pointer in retdata".to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (address, value) in writes {
+            self.storage.insert((contract.clone(), address), value);
+        }
+        self.events.extend(events.iter().cloned());
+        Ok(CallInfo { retdata, events })
+    }
+}
+
+
+// == Syscalls == //
+
+// hint codes of starkware/starknet/common/syscalls.cairo
+const SYSCALL_PREFIX: &str = "syscall_handler.";
+const SYSCALL_SUFFIX: &str = "(segments=segments, syscall_ptr=ids.syscall_ptr)";
+
+// Cairo 1 syscall requests start with the selector and the gas, the response follows the request
+// with the gas left and a failure flag : [gas, 0, values...] or [gas, 1, reason start, reason end]
+const CAIRO1_REQUEST_SIZES: [(&str, usize); 4] =
+    [("StorageRead", 4), ("StorageWrite", 5), ("EmitEvent", 6), ("GetExecutionInfo", 2)];
+
+// The compiler hints of the contract, a Cairo 1 contract also gets its syscalls as hints
+enum Hints {
+    Cairo0(BuiltinHintProcessor),
+    Cairo1(Cairo1HintProcessor),
+}
+
+// Serves the syscalls of one invoke or call, the other hints go to the processor of the contract
+struct SyscallHandler<'a> {
+    state: &'a StarknetState,
+    contract_address: Felt252,
+    caller_address: Felt252,
+    selector: Felt252,
+    // applied to the state once the invoke succeeded
+    writes: HashMap<Felt252, Felt252>,
+    events: Vec<Event>,
+    hints: Hints,
+}
+
+fn syscall_error(msg: String) -> HintError {
+    HintError::CustomHint(msg.into())
+}
+
+fn at(ptr: Relocatable, offset: usize) -> Relocatable {
+    Relocatable::from((ptr.segment_index, ptr.offset + offset))
+}
+
+fn read_felt(vm: &VirtualMachine, ptr: Relocatable) -> Result<Felt252, HintError> {
+    Ok(vm.get_integer(ptr)?.into_owned())
+}
+
+fn read_array(vm: &VirtualMachine, len: &Felt252, ptr: Relocatable) -> Result<Vec<Felt252>, HintError> {
+    let len = usize::try_from(len.to_biguint()).map_err(|_| syscall_error(format!("array length {} is too large", len)))?;
+    (0..len).map(|i| read_felt(vm, at(ptr, i))).collect()
+}
+
+// a Cairo 1 span, its start and end pointers at 'ptr'
+fn read_span(vm: &VirtualMachine, ptr: Relocatable) -> Result<Vec<Felt252>, HintError> {
+    let (start, end) = (vm.get_relocatable(ptr)?, vm.get_relocatable(at(ptr, 1))?);
+    let len = end.offset.checked_sub(start.offset).ok_or_else(|| syscall_error("span ends before it starts".to_string()))?;
+    read_array(vm, &Felt252::from(len), start)
+}
+
+// the address in 'system' : [cell] or [cell] + offset
+fn cell_value(vm: &VirtualMachine, cell: &CellRef) -> Result<Relocatable, HintError> {
+    let base = match cell.register {
+        Register::AP => vm.get_ap(),
+        Register::FP => vm.get_fp(),
+    };
+    let offset = base.offset as isize + cell.offset as isize;
+    Ok(vm.get_relocatable(Relocatable::from((base.segment_index, offset as usize)))?)
+}
+
+fn syscall_ptr(vm: &VirtualMachine, system: &ResOperand) -> Result<Relocatable, HintError> {
+    match system {
+        ResOperand::Deref(cell) => cell_value(vm, cell),
+        ResOperand::BinOp(BinOpOperand { op: Operation::Add, a, b: DerefOrImmediate::Immediate(offset) }) => {
+            let offset = usize::try_from(&offset.value).map_err(|_| syscall_error(format!("bad syscall offset {}", offset.value)))?;
+            Ok(at(cell_value(vm, a)?, offset))
+        }
+        _ => Err(syscall_error(format!("unsupported syscall pointer {}", system))),
+    }
+}
+
+impl<'a> SyscallHandler<'a> {
+    fn new(state: &'a StarknetState, contract_address: Felt252, caller_address: Felt252, selector: Felt252, hints: Hints) -> Self {
+        Self { state, contract_address, caller_address, selector, writes: HashMap::new(), events: vec![], hints }
+    }
+
+    fn read(&self, address: &Felt252) -> Felt252 {
+        match self.writes.get(address) {
+            Some(value) => value.clone(),
+            None => self.state.storage(&self.contract_address, address),
+        }
+    }
+
+    // 'ptr' points to the request, the response follows it
+    fn syscall(&mut self, name: &str, vm: &mut VirtualMachine, ptr: Relocatable) -> Result<(), HintError> {
+        let selector = match name {
+            "storage_read" => "StorageRead",
+            "storage_write" => "StorageWrite",
+            "emit_event" => "EmitEvent",
+            "get_caller_address" => "GetCallerAddress",
+            _ => return Err(syscall_error(format!("unsupported syscall {}", name))),
+        };
+        if read_felt(vm, ptr)? != short_string(selector) {
+            return Err(syscall_error(format!("{} called with the selector of another syscall", name)));
+        }
+
+        match name {
+            // StorageRead { selector, address, value }
+            "storage_read" => {
+                let value = self.read(&read_felt(vm, at(ptr, 1))?);
+                vm.insert_value(at(ptr, 2), value)?;
+            }
+            // StorageWrite { selector, address, value }
+            "storage_write" => {
+                let address = read_felt(vm, at(ptr, 1))?;
+                let value = read_felt(vm, at(ptr, 2))?;
+                self.writes.insert(address, value);
+            }
+            // EmitEvent { selector, keys_len, keys, data_len, data }
+            "emit_event" => {
+                let keys = read_array(vm, &read_felt(vm, at(ptr, 1))?, vm.get_relocatable(at(ptr, 2))?)?;
+                let data = read_array(vm, &read_felt(vm, at(ptr, 3))?, vm.get_relocatable(at(ptr, 4))?)?;
+                self.events.push(Event { from_address: self.contract_address.clone(), keys, data });
+            }
+            // GetCallerAddress { selector, caller_address }
+            _ => vm.insert_value(at(ptr, 1), self.caller_address.clone())?,
+        }
+        Ok(())
+    }
+
+    // 'ptr' points to [selector, gas, request...], no gas is charged
+    fn syscall_cairo1(&mut self, vm: &mut VirtualMachine, ptr: Relocatable) -> Result<(), HintError> {
+        let selector = read_felt(vm, ptr)?;
+        let (name, request_size) = CAIRO1_REQUEST_SIZES
+            .iter()
+            .find(|(name, _)| short_string(name) == selector)
+            .ok_or_else(|| syscall_error(format!("unsupported syscall {}", selector)))?;
+        // StorageRead and StorageWrite take [address domain, address, ...], only domain 0 exists
+        if matches!(*name, "StorageRead" | "StorageWrite") && read_felt(vm, at(ptr, 2))? != Felt252::from(0) {
+            return Err(syscall_error("unsupported address domain".to_string()));
+        }
+
+        let mut response = vec![MaybeRelocatable::from(read_felt(vm, at(ptr, 1))?), MaybeRelocatable::from(0)];
+        match *name {
+            // [address domain, address] -> [value]
+            "StorageRead" => response.push(self.read(&read_felt(vm, at(ptr, 3))?).into()),
+            // [address domain, address, value]
+            "StorageWrite" => {
+                let address = read_felt(vm, at(ptr, 3))?;
+                let value = read_felt(vm, at(ptr, 4))?;
+                self.writes.insert(address, value);
+            }
+            // [keys span, data span]
+            "EmitEvent" => {
+                let keys = read_span(vm, at(ptr, 2))?;
+                let data = read_span(vm, at(ptr, 4))?;
+                self.events.push(Event { from_address: self.contract_address.clone(), keys, data });
+            }
+            // [] -> [ExecutionInfo*] : block info and tx info are zero, the signature empty
+            _ => {
+                let zero = MaybeRelocatable::from(0);
+                let block_info = vm.add_memory_segment();
+                vm.load_data(block_info, &vec![zero.clone(); 3])?;
+                let signature = vm.add_memory_segment();
+                let tx_info = vm.add_memory_segment();
+                vm.load_data(
+                    tx_info,
+                    &vec![
+                        zero.clone(),
+                        self.caller_address.clone().into(),
+                        zero.clone(),
+                        signature.into(),
+                        signature.into(),
+                        zero.clone(),
+                        zero.clone(),
+                        zero,
+                    ],
+                )?;
+                let info = vm.add_memory_segment();
+                vm.load_data(
+                    info,
+                    &vec![
+                        block_info.into(),
+                        tx_info.into(),
+                        self.caller_address.clone().into(),
+                        self.contract_address.clone().into(),
+                        self.selector.clone().into(),
+                    ],
+                )?;
+                response.push(info.into());
+            }
+        }
+        vm.load_data(at(ptr, *request_size), &response)?;
+        Ok(())
+    }
+
+    fn tracker(&self) -> &dyn ResourceTracker {
+        match &self.hints {
+            Hints::Cairo0(hints) => hints,
+            Hints::Cairo1(hints) => hints,
+        }
+    }
+
+    fn tracker_mut(&mut self) -> &mut dyn ResourceTracker {
+        match &mut self.hints {
+            Hints::Cairo0(hints) => hints,
+            Hints::Cairo1(hints) => hints,
+        }
+    }
+}
+
+impl HintProcessorLogic for SyscallHandler<'_> {
+    fn compile_hint(
+        &self,
+        hint_code: &str,
+        ap_tracking_data: &ApTracking,
+        reference_ids: &HashMap<String, usize>,
+        references: &[HintReference],
+    ) -> Result<Box<dyn Any>, VirtualMachineError> {
+        match &self.hints {
+            Hints::Cairo0(hints) => hints.compile_hint(hint_code, ap_tracking_data, reference_ids, references),
+            Hints::Cairo1(hints) => hints.compile_hint(hint_code, ap_tracking_data, reference_ids, references),
+        }
+    }
+
+    fn execute_hint(
+        &mut self,
+        vm: &mut VirtualMachine,
+        exec_scopes: &mut ExecutionScopes,
+        hint_data: &Box<dyn Any>,
+        constants: &HashMap<String, Felt252>,
+    ) -> Result<(), HintError> {
+        // Cairo 1 : the hints at this pc, see Cairo1HintProcessor::compile_hint
+        if let Some(hints) = hint_data.downcast_ref::<Vec<Hint>>() {
+            for hint in hints {
+                if let Hint::Starknet(StarknetHint::SystemCall { system }) = hint {
+                    let ptr = syscall_ptr(vm, system)?;
+                    self.syscall_cairo1(vm, ptr)?;
+                } else if let Hints::Cairo1(processor) = &self.hints {
+                    processor.execute(vm, exec_scopes, hint)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let data = hint_data.downcast_ref::<HintProcessorData>().ok_or(HintError::WrongHintData)?;
+        match data.code.strip_prefix(SYSCALL_PREFIX).and_then(|code| code.strip_suffix(SYSCALL_SUFFIX)) {
+            Some(name) => {
+                let ptr = get_ptr_from_var_name("syscall_ptr", vm, &data.ids_data, &data.ap_tracking)?;
+                self.syscall(name, vm, ptr)
+            }
+            None => match &mut self.hints {
+                Hints::Cairo0(builtin) => builtin.execute_hint(vm, exec_scopes, hint_data, constants),
+                Hints::Cairo1(_) => Err(HintError::WrongHintData),
+            },
+        }
+    }
+}
+
+impl ResourceTracker for SyscallHandler<'_> {
+    fn consumed(&self) -> bool {
+        self.tracker().consumed()
+    }
+
+    fn consume_step(&mut self) {
+        self.tracker_mut().consume_step()
+    }
+
+    fn get_n_steps(&self) -> Option<usize> {
+        self.tracker().get_n_steps()
+    }
+
+    fn run_resources(&self) -> &RunResources {
+        self.tracker().run_resources()
+    }
+}
+
+// The tests running dispute_contract.cairo need its build :
+//   cairo_program/compile.sh && cargo test dispute_contract -- --ignored
+#[cfg(test)]
+fn dispute_contract(state: &mut StarknetState) -> Felt252 {
+    state.deploy(super::registry::executor("dispute_contract").unwrap())
+}
+
+// storage address of roots(batch), H(ROOTS, batch)
+#[cfg(test)]
+fn root_address(batch: u64) -> Felt252 {
+    use starknet_crypto::{pedersen_hash, FieldElement};
+
+    let roots = FieldElement::from_byte_slice_be(b"roots").unwrap();
+    super::dispute::to_felt(&pedersen_hash(&roots, &FieldElement::from(batch)))
+}
+
+// claim (by value), leaf, siblings_len, siblings
+#[cfg(test)]
+fn dispute_args(claim: &super::dispute::Claim, opening: &super::dispute::BatchOpening) -> Vec<Argument> {
+    use super::dispute::to_felt;
+
+    let mut args = claim.to_felts().into_iter().map(Argument::Felt).collect::<Vec<_>>();
+    args.push(Argument::Felt(to_felt(&opening.leaf)));
+    args.push(Argument::felt(opening.siblings.len() as u64));
+    args.push(Argument::Array(opening.siblings.iter().map(to_felt).collect()));
+    args
+}
+
+#[test]
+#[ignore]
+pub fn test_storage_and_caller() {
+    use starknet_crypto::FieldElement;
+
+    use super::dispute::{mul, random_scalar, to_felt};
+
+    let mut state = StarknetState::default();
+    let contract = dispute_contract(&mut state);
+    let (sequencer, user) = (Felt252::from(100u64), Felt252::from(200u64));
+    let key = mul(&starknet_curve::curve_params::GENERATOR, &random_scalar(&mut rand::rngs::OsRng));
+    let key_args = [Argument::Felt(to_felt(&key.x)), Argument::Felt(to_felt(&key.y))];
+
+    let call = state.invoke(&sequencer, &contract, "register", &key_args).unwrap();
+    assert!(call.retdata.is_empty() && call.events.is_empty());
+    assert_eq!(state.storage(&contract, &short_string("sequencer")), sequencer);
+    assert_eq!(state.storage(&contract, &(short_string("sequencer_key") + Felt252::from(1))), to_felt(&key.y));
+
+    // registers once, a failed invoke changes nothing
+    assert!(matches!(state.invoke(&user, &contract, "register", &key_args), Err(CairoError::Run(_))));
+    assert_eq!(state.storage(&contract, &short_string("sequencer")), sequencer);
+
+    // only the sequencer posts roots, once per batch
+    let root = FieldElement::from(7u64);
+    let args = [Argument::felt(3), Argument::Felt(to_felt(&root))];
+    assert!(state.invoke(&user, &contract, "post_root", &args).is_err());
+    assert_eq!(state.storage(&contract, &root_address(3)), Felt252::from(0));
+    state.invoke(&sequencer, &contract, "post_root", &args).unwrap();
+    assert_eq!(state.storage(&contract, &root_address(3)), to_felt(&root));
+    let args = [Argument::felt(3), Argument::felt(8)];
+    assert!(matches!(state.invoke(&sequencer, &contract, "post_root", &args), Err(CairoError::Run(_))));
+    assert_eq!(state.storage(&contract, &root_address(3)), to_felt(&root));
+
+    let call = state.invoke(&user, &contract, "slashed", &[]).unwrap();
+    assert_eq!(call.retdata, vec![Felt252::from(0)]);
+    assert!(matches!(state.invoke(&user, &Felt252::from(9), "slashed", &[]), Err(CairoError::ContractNotFound(_))));
+}
+
+#[test]
+#[ignore]
+pub fn test_dispute_contract() {
+    use rand::rngs::OsRng;
+    use starknet_crypto::FieldElement;
+
+    use super::dispute::{cosign, random_scalar, to_felt, Batch, BATCH_DEPTH};
+
+    let mut state = StarknetState::default();
+    let contract = dispute_contract(&mut state);
+    let (sequencer, user) = (Felt252::from(100u64), Felt252::from(200u64));
+
+    let (user_secret, sequencer_secret) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let message = FieldElement::from(7u64);
+    let claim = cosign(&mut OsRng, &user_secret, &sequencer_secret, message, 42);
+    let key_args = [Argument::Felt(to_felt(&claim.sequencer_key.x)), Argument::Felt(to_felt(&claim.sequencer_key.y))];
+    state.invoke(&sequencer, &contract, "register", &key_args).unwrap();
+
+    // batch 0 holds 7 at 42, batch 1 holds 8 at 42 + 2^32
+    let honest = Batch::new(&[message; 43]);
+    let dishonest = Batch::new(&[FieldElement::from(8u64); 43]);
+    for (batch, posted) in [(0u64, &honest), (1, &dishonest)] {
+        state.invoke(&sequencer, &contract, "post_root", &[Argument::felt(batch), Argument::Felt(to_felt(&posted.root()))]).unwrap();
+    }
+
+    // included where committed : no fraud, and no opening makes it one
+    let call = state.invoke(&user, &contract, "dispute", &dispute_args(&claim, &honest.open(42))).unwrap();
+    assert_eq!(call.retdata, vec![Felt252::from(0)]);
+    assert!(call.events.is_empty());
+    assert!(matches!(state.invoke(&user, &contract, "dispute", &dispute_args(&claim, &dishonest.open(42))), Err(CairoError::Run(_))));
+
+    // a claim signed with another key than the registered one is rejected
+    let other = cosign(&mut OsRng, &user_secret, &random_scalar(&mut OsRng), message, 42);
+    assert!(matches!(state.invoke(&user, &contract, "dispute", &dispute_args(&other, &honest.open(42))), Err(CairoError::Run(_))));
+
+    // the sequencer co-signed 7 at 42 + 2^32 but batch 1 holds 8 there : slashed, with a Fraud event
+    let index = 42 + (1u64 << BATCH_DEPTH);
+    let claim = cosign(&mut OsRng, &user_secret, &sequencer_secret, message, index);
+    let call = state.invoke(&user, &contract, "dispute", &dispute_args(&claim, &dishonest.open(index))).unwrap();
+    assert_eq!(call.retdata, vec![Felt252::from(1)]);
+    let fraud = Event {
+        from_address: contract.clone(),
+        keys: vec![short_string("Fraud")],
+        data: vec![sequencer, to_felt(&message), Felt252::from(index), Felt252::from(8)],
+    };
+    assert_eq!(call.events, vec![fraud.clone()]);
+    assert_eq!(state.events(), &[fraud]);
+    assert_eq!(state.storage(&contract, &short_string("slashed")), Felt252::from(1));
+}
+
+#[test]
+#[ignore]
+pub fn test_cairo1_dispute_contract() {
+    use rand::rngs::OsRng;
+    use starknet_crypto::{pedersen_hash, FieldElement};
+
+    use super::dispute::{cosign, random_scalar, to_felt, Batch, BatchOpening, Claim, BATCH_DEPTH};
+
+    let mut state = StarknetState::default();
+    let contract = state.deploy(super::registry::contract("dispute_contract_cairo1").unwrap());
+    let (sequencer, user) = (Felt252::from(100u64), Felt252::from(200u64));
+    assert!(matches!(state.invoke(&user, &contract, "slashed", &[]), Err(CairoError::WrongContractKind(_))));
+
+    let (user_secret, sequencer_secret) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let message = FieldElement::from(7u64);
+    let claim = cosign(&mut OsRng, &user_secret, &sequencer_secret, message, 42);
+    let key = [to_felt(&claim.sequencer_key.x), to_felt(&claim.sequencer_key.y)];
+    let call = state.call(&sequencer, &contract, "register", &key).unwrap();
+    assert!(call.retdata.is_empty() && call.events.is_empty());
+    assert_eq!(state.storage(&contract, &sn_keccak("sequencer")), sequencer);
+    assert_eq!(state.storage(&contract, &(sn_keccak("sequencer_key") + Felt252::from(1))), key[1]);
+
+    // registers once, a failed call changes nothing
+    let err = state.call(&user, &contract, "register", &key).err().unwrap();
+    assert!(matches!(err, CairoError::Panic(data) if data == [short_string("already registered")]));
+    assert_eq!(state.storage(&contract, &sn_keccak("sequencer")), sequencer);
+
+    // only the sequencer posts roots, once per batch, at roots(batch)
+    let roots = FieldElement::from_byte_slice_be(&sn_keccak("roots").to_bytes_be()).unwrap();
+    let root_address = |batch: u64| to_felt(&pedersen_hash(&roots, &FieldElement::from(batch)));
+    let honest = Batch::new(&[message; 43]);
+    let dishonest = Batch::new(&[FieldElement::from(8u64); 43]);
+    let post = |batch: u64, posted: &Batch| [Felt252::from(batch), to_felt(&posted.root())];
+    assert!(matches!(state.call(&user, &contract, "post_root", &post(0, &honest)), Err(CairoError::Panic(_))));
+    assert_eq!(state.storage(&contract, &root_address(0)), Felt252::from(0));
+    state.call(&sequencer, &contract, "post_root", &post(0, &honest)).unwrap();
+    assert_eq!(state.storage(&contract, &root_address(0)), to_felt(&honest.root()));
+    let err = state.call(&sequencer, &contract, "post_root", &post(0, &dishonest)).err().unwrap();
+    assert!(matches!(err, CairoError::Panic(data) if data == [short_string("root already posted")]));
+    state.call(&sequencer, &contract, "post_root", &post(1, &dishonest)).unwrap();
+
+    // claim, leaf, siblings as a span
+    let calldata = |claim: &Claim, opening: &BatchOpening| {
+        let mut calldata = claim.to_felts();
+        calldata.extend([to_felt(&opening.leaf), Felt252::from(opening.siblings.len() as u64)]);
+        calldata.extend(opening.siblings.iter().map(to_felt));
+        calldata
+    };
+
+    // included where committed : no fraud, and no opening makes it one
+    let call = state.call(&user, &contract, "dispute", &calldata(&claim, &honest.open(42))).unwrap();
+    assert_eq!(call.retdata, vec![Felt252::from(0)]);
+    assert!(call.events.is_empty());
+    let err = state.call(&user, &contract, "dispute", &calldata(&claim, &dishonest.open(42))).err().unwrap();
+    assert!(matches!(err, CairoError::Panic(data) if data == [short_string("invalid opening")]));
+
+    // a claim signed with another key than the registered one is rejected
+    let other = cosign(&mut OsRng, &user_secret, &random_scalar(&mut OsRng), message, 42);
+    let err = state.call(&user, &contract, "dispute", &calldata(&other, &honest.open(42))).err().unwrap();
+    assert!(matches!(err, CairoError::Panic(data) if data == [short_string("unknown sequencer key")]));
+
+    // the sequencer co-signed 7 at 42 + 2^32 but batch 1 holds 8 there : slashed, with a Fraud event
+    let index = 42 + (1u64 << BATCH_DEPTH);
+    let claim = cosign(&mut OsRng, &user_secret, &sequencer_secret, message, index);
+    let call = state.call(&user, &contract, "dispute", &calldata(&claim, &dishonest.open(index))).unwrap();
+    assert_eq!(call.retdata, vec![Felt252::from(1)]);
+    let fraud = Event {
+        from_address: contract.clone(),
+        keys: vec![sn_keccak("Fraud")],
+        data: vec![sequencer, to_felt(&message), Felt252::from(index), Felt252::from(8)],
+    };
+    assert_eq!(call.events, vec![fraud.clone()]);
+    assert_eq!(state.events(), &[fraud]);
+    assert_eq!(state.call(&user, &contract, "slashed", &[]).unwrap().retdata, vec![Felt252::from(1)]);
+}
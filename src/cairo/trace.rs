@@ -0,0 +1,347 @@
+use std::fmt;
+use std::path::Path;
+
+use cairo_vm::felt::Felt252;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::BuiltinHintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessor;
+use cairo_vm::types::instruction::{ApUpdate, Instruction, Op1Addr, Opcode, PcUpdate, Register, Res};
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::decoding::decoder::decode_instruction;
+use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::vm_exception::{get_location, VmException};
+use cairo_vm::vm::runners::cairo_runner::CairoRunner;
+use cairo_vm::vm::vm_core::VirtualMachine;
+
+use super::{Argument, CairoError, CairoExecutor, Value};
+
+// Step by step trace of a Cairo 0 run, to see where and why a program fails :
+//   let trace = executor.trace("verify_claim", &args)?;
+//   println!("{}", trace.report());     // the failing assertion, its source and the registers
+//   trace.dump(Path::new("trace.txt"))?;  // every step
+// A step has its registers (pc in the program segment, ap and fp in the execution segment), its
+// instruction disassembled and its location in the Cairo source, from the debug_info of the program.
+
+// == Steps == //
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub pc: usize,
+    pub ap: usize,
+    pub fp: usize,
+    pub instruction: String,
+    // file:line:col, None without debug_info
+    pub location: Option<String>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pc=0:{} ap=1:{} fp=1:{}  {}", self.pc, self.ap, self.fp, self.instruction)?;
+        if let Some(location) = &self.location {
+            write!(f, "  ({})", location)?;
+        }
+        Ok(())
+    }
+}
+
+fn decode(vm: &VirtualMachine, pc: usize) -> Option<Instruction> {
+    let encoded = u64::try_from(vm.get_integer(Relocatable::from((0, pc))).ok()?.to_biguint()).ok()?;
+    decode_instruction(encoded).ok()
+}
+
+// felts above p / 2 are shown as negative numbers, like cairo-compile does for immediates
+fn signed(value: &Felt252) -> String {
+    let negated = -value.clone();
+    if negated.to_biguint() < value.to_biguint() {
+        format!("-{}", negated)
+    } else {
+        value.to_string()
+    }
+}
+
+fn offset(offset: isize) -> String {
+    match offset {
+        0 => String::new(),
+        offset if offset < 0 => format!(" - {}", -offset),
+        offset => format!(" + {}", offset),
+    }
+}
+
+fn cell(register: Register, off: isize) -> String {
+    let register = match register {
+        Register::AP => "ap",
+        Register::FP => "fp",
+    };
+    format!("[{}{}]", register, offset(off))
+}
+
+// Cairo assembly of the instruction at pc, e.g. "[ap] = [fp - 3] + 1, ap++"
+fn disassemble(vm: &VirtualMachine, pc: usize) -> String {
+    let instruction = match decode(vm, pc) {
+        Some(instruction) => instruction,
+        None => return "??".to_string(),
+    };
+    let dst = cell(instruction.dst_register, instruction.off0);
+    let op0 = cell(instruction.op0_register, instruction.off1);
+    let op1 = match instruction.op1_addr {
+        Op1Addr::Imm => match vm.get_integer(Relocatable::from((0, pc + 1))) {
+            Ok(value) => signed(&value),
+            Err(_) => "??".to_string(),
+        },
+        Op1Addr::AP => cell(Register::AP, instruction.off2),
+        Op1Addr::FP => cell(Register::FP, instruction.off2),
+        Op1Addr::Op0 => format!("[{}{}]", op0, offset(instruction.off2)),
+    };
+    let res = match instruction.res {
+        Res::Op1 | Res::Unconstrained => op1.clone(),
+        Res::Add => format!("{} + {}", op0, op1),
+        Res::Mul => format!("{} * {}", op0, op1),
+    };
+    let mut text = match (instruction.opcode, instruction.pc_update) {
+        (Opcode::AssertEq, _) => format!("{} = {}", dst, res),
+        (Opcode::Call, PcUpdate::JumpRel) => format!("call rel {}", res),
+        (Opcode::Call, _) => format!("call abs {}", res),
+        (Opcode::Ret, _) => "ret".to_string(),
+        (Opcode::NOp, PcUpdate::Jump) => format!("jmp abs {}", res),
+        (Opcode::NOp, PcUpdate::JumpRel) => format!("jmp rel {}", res),
+        (Opcode::NOp, PcUpdate::Jnz) => format!("jmp rel {} if {} != 0", op1, dst),
+        (Opcode::NOp, PcUpdate::Regular) if instruction.ap_update == ApUpdate::Add => format!("ap += {}", res),
+        (Opcode::NOp, PcUpdate::Regular) => "nop".to_string(),
+    };
+    if instruction.ap_update == ApUpdate::Add1 {
+        text.push_str(", ap++");
+    }
+    text
+}
+
+// The operands of the instruction at pc that are set, as (cell, value)
+fn operands(vm: &VirtualMachine, pc: usize, ap: usize, fp: usize) -> Vec<(String, String)> {
+    let instruction = match decode(vm, pc) {
+        Some(instruction) => instruction,
+        None => return vec![],
+    };
+    let address = |register: Register, off: isize| {
+        let base = match register {
+            Register::AP => ap,
+            Register::FP => fp,
+        };
+        base.checked_add_signed(off).map(|offset| Relocatable::from((1, offset)))
+    };
+    let mut cells = vec![
+        (cell(instruction.dst_register, instruction.off0), address(instruction.dst_register, instruction.off0)),
+        (cell(instruction.op0_register, instruction.off1), address(instruction.op0_register, instruction.off1)),
+    ];
+    match instruction.op1_addr {
+        Op1Addr::Imm => {}
+        Op1Addr::AP => cells.push((cell(Register::AP, instruction.off2), address(Register::AP, instruction.off2))),
+        Op1Addr::FP => cells.push((cell(Register::FP, instruction.off2), address(Register::FP, instruction.off2))),
+        Op1Addr::Op0 => {
+            let op0 = cells[1].1.and_then(|op0| vm.get_relocatable(op0).ok());
+            let op1 = op0.and_then(|op0| (op0 + instruction.off2 as i32).ok());
+            cells.push((format!("[{}{}]", cells[1].0, offset(instruction.off2)), op1));
+        }
+    }
+    cells
+        .into_iter()
+        .filter_map(|(name, address)| Some((name, vm.get_maybe(&address?)?.to_string())))
+        .collect()
+}
+
+fn step(runner: &CairoRunner, vm: &VirtualMachine, pc: usize, ap: usize, fp: usize) -> Step {
+    let location = get_location(pc, runner, None).map(|location| location.to_string(""));
+    Step { pc, ap, fp, instruction: disassemble(vm, pc), location }
+}
+
+
+// == Trace == //
+
+#[derive(Debug)]
+pub struct Trace {
+    // the steps that went through
+    pub steps: Vec<Step>,
+    pub return_values: Vec<Value>,
+    pub failure: Option<Failure>,
+}
+
+// Where a run stopped
+#[derive(Debug)]
+pub struct Failure {
+    // the failing step, after the last of Trace::steps
+    pub step: Step,
+    // e.g. "An ASSERT_EQ instruction failed: 0 != 1."
+    pub message: String,
+    // set operands of the failing instruction, e.g. ("[fp - 3]", "1:4")
+    pub operands: Vec<(String, String)>,
+    // the failing line, marked, when the source file can be read
+    pub source: Option<String>,
+    pub traceback: Option<String>,
+    pub error: CairoError,
+}
+
+impl CairoExecutor {
+    pub fn trace(&self, name: &str, args: &[Argument]) -> Result<Trace, CairoError> {
+        self.trace_with_hints(name, args, &mut BuiltinHintProcessor::new_empty())
+    }
+
+    // Err only when the run doesn't start (see run) : a failing program gives a Trace with a failure
+    pub fn trace_with_hints(
+        &self,
+        name: &str,
+        args: &[Argument],
+        hint_processor: &mut dyn HintProcessor,
+    ) -> Result<Trace, CairoError> {
+        let mut vm = VirtualMachine::new(true);
+        let mut runner = CairoRunner::new(&self.program, self.layout.as_str(), false)?;
+        let result = match self.execute(name, args, &mut runner, &mut vm, hint_processor) {
+            Err(error) if exception(&error).is_none() => return Err(error),
+            result => result,
+        };
+
+        // the relocated trace has pc + 1, and ap, fp after the program segment
+        runner.relocate(&mut vm, false).map_err(CairoRunError::from)?;
+        let execution_base = 1 + vm.get_segment_size(0).unwrap_or(0);
+        let steps = vm
+            .get_relocated_trace()
+            .map_err(CairoRunError::from)?
+            .iter()
+            .map(|entry| step(&runner, &vm, entry.pc - 1, entry.ap - execution_base, entry.fp - execution_base))
+            .collect();
+
+        Ok(match result {
+            Ok(return_values) => Trace { steps, return_values, failure: None },
+            Err(error) => Trace { steps, return_values: vec![], failure: Some(Failure::new(&runner, &vm, error)) },
+        })
+    }
+}
+
+// the error of a run that failed in the VM
+fn exception(error: &CairoError) -> Option<&VmException> {
+    match error {
+        CairoError::Run(error) => match error.as_ref() {
+            CairoRunError::VmException(exception) => Some(exception),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Failure {
+    fn new(runner: &CairoRunner, vm: &VirtualMachine, error: CairoError) -> Self {
+        let (pc, ap, fp) = (vm.get_pc().offset, vm.get_ap().offset, vm.get_fp().offset);
+        let (message, source, traceback) = match exception(&error) {
+            Some(exception) => {
+                let source = exception.inst_location.as_ref().and_then(|location| {
+                    let contents = std::fs::read(&location.input_file.filename).ok()?;
+                    Some(location.get_location_marks(&contents))
+                });
+                (exception.inner_exc.to_string(), source, exception.traceback.clone())
+            }
+            None => (error.to_string(), None, None),
+        };
+        let (step, operands) = (step(runner, vm, pc, ap, fp), operands(vm, pc, ap, fp));
+        Failure { step, message, operands, source, traceback, error }
+    }
+}
+
+impl Trace {
+    // What failed, where in the source and with which registers, and the steps before
+    pub fn report(&self) -> String {
+        let failure = match &self.failure {
+            Some(failure) => failure,
+            None => return format!("returned {:?} after {} steps", self.return_values, self.steps.len()),
+        };
+        let mut report = format!("failed at step {} : {}\n", self.steps.len(), failure.message);
+        if let Some(location) = &failure.step.location {
+            report.push_str(&format!("{}\n", location));
+        }
+        if let Some(source) = &failure.source {
+            report.push_str(&format!("{}\n", source));
+        }
+        report.push_str(&format!("{}\n", failure.step));
+        for (cell, value) in &failure.operands {
+            report.push_str(&format!("  {} = {}\n", cell, value));
+        }
+        if let Some(traceback) = &failure.traceback {
+            report.push_str(traceback);
+        }
+        report.push_str("last steps :\n");
+        for (i, step) in self.steps.iter().enumerate().skip(self.steps.len().saturating_sub(5)) {
+            report.push_str(&format!("{:>6} {}\n", i, step));
+        }
+        report
+    }
+
+    pub fn dump(&self, path: &Path) -> Result<(), CairoError> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+}
+
+// One line per step, the failing one last
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{:>6} {}", i, step)?;
+        }
+        if let Some(failure) = &self.failure {
+            writeln!(f, "{:>6} {}  <- {}", self.steps.len(), failure.step, failure.message)?;
+        }
+        Ok(())
+    }
+}
+
+// Traced dispute verification, for the 'debug' command : a fraudulent claim, or with 'forged' a
+// claim whose response was tampered with, which the contract rejects
+//   cargo run --release -- debug [--forged] [--trace-file trace.txt]
+pub fn debug_dispute(forged: bool, trace_file: Option<&Path>) -> Result<String, CairoError> {
+    use rand::rngs::OsRng;
+    use starknet_crypto::FieldElement;
+
+    use super::dispute::{cosign, random_scalar, trace_dispute, Batch};
+
+    let (user, sequencer) = (random_scalar(&mut OsRng), random_scalar(&mut OsRng));
+    let mut claim = cosign(&mut OsRng, &user, &sequencer, FieldElement::from(7u64), 42);
+    if forged {
+        claim.response += FieldElement::ONE;
+    }
+    // the sequencer posted 8 at 42
+    let opening = Batch::new(&[FieldElement::from(8u64); 43]).open(42);
+    let trace = trace_dispute(&claim, &opening)?;
+    if let Some(path) = trace_file {
+        trace.dump(path)?;
+    }
+    Ok(trace.report())
+}
+
+
+#[test]
+pub fn test_trace() {
+    use super::registry;
+
+    let executor = registry::executor("abs_value_array").unwrap();
+    let args = [Argument::array([-7i64, -10]), Argument::array([7, 10]), Argument::felt(2), Argument::felt(0)];
+    let trace = executor.trace("compare_abs_arrays", &args).unwrap();
+    let run = executor.run("compare_abs_arrays", &args).unwrap();
+    assert!(trace.failure.is_none());
+    assert_eq!(trace.return_values, run.return_values());
+    assert_eq!(trace.steps.len(), run.resources().unwrap().steps);
+
+    // compare_abs_arrays starts with 'if (iterator == array_length)', iterator - array_length in [ap]
+    let first = &trace.steps[0];
+    assert_eq!(first.pc, executor.entrypoint("compare_abs_arrays").unwrap().pc);
+    assert_eq!(first.instruction, "[fp - 3] = [ap] + [fp - 4], ap++");
+    assert_eq!(first.location.as_deref(), Some("cairo_program/abs_value_array.cairo:10:9"));
+
+    // fill_array asserts array[0] = base, 1 != 0
+    let args = [Argument::array([1i64, 2]), Argument::felt(0), Argument::felt(1), Argument::felt(2), Argument::felt(0)];
+    let trace = executor.trace("fill_array", &args).unwrap();
+    let failure = trace.failure.as_ref().unwrap();
+    assert!(matches!(failure.error, CairoError::Run(_)));
+    assert_eq!(failure.step.location.as_deref(), Some("cairo_program/abs_value_array.cairo:25:5"));
+    assert!(failure.message.contains("0 != 1"));
+    assert!(failure.operands.contains(&("[[ap - 3]]".to_string(), "1".to_string())));
+    let report = trace.report();
+    assert!(report.contains("cairo_program/abs_value_array.cairo:25:5"));
+    assert!(trace.to_string().lines().last().unwrap().contains("<- "));
+
+    // the run doesn't start
+    assert!(matches!(executor.trace("missing", &[]), Err(CairoError::EntrypointNotFound(_))));
+}
@@ -0,0 +1,294 @@
+use halo2_gadgets::ecc::chip::EccChip;
+use halo2_gadgets::ecc::{NonIdentityPoint, ScalarVar};
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::pasta::pallas;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+use halo2_pse::halo2curves::ff::Field;
+use halo2_pse::halo2curves::group::Curve;
+use rand::{CryptoRng, RngCore};
+
+use super::fixed_bases::{self, SequencerFixedBases};
+use super::schnorr::{
+    base_to_scalar, constrain_signature_equation, coordinates, hash, load_private, native_hash, SignatureConfig,
+};
+
+// Proof that the sequencer equivocated : it co-signed two different messages m != m'
+// for the same index i. Each aggregated signature (R, s) is checked as in the protocol
+//   l = H(P_u || P_s),  w_u = H(l || P_u),  w_s = H(l || P_s)
+//   X = w_u * P_u + w_s * P_s
+//   e = H(R || X || m || i)
+//   s * G == R + e * X
+// Public inputs : the sequencer key P_s and the index i. The users' keys, the messages
+// and the signatures stay private, so the contract can slash without learning the txs.
+
+pub const K: u32 = 12;
+
+pub const SEQUENCER_X: usize = 0;
+pub const SEQUENCER_Y: usize = 1;
+pub const INDEX: usize = 2;
+
+
+// == Native protocol on Pallas (witness generation) == //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AggregatedSignature {
+    pub user_key: pallas::Affine,
+    pub commit: pallas::Affine,
+    pub response: pallas::Scalar,
+}
+
+// (w_u, w_s)
+pub fn weights(user_key: &pallas::Affine, sequencer_key: &pallas::Affine) -> (pallas::Base, pallas::Base) {
+    let (ux, uy) = coordinates(user_key);
+    let (sx, sy) = coordinates(sequencer_key);
+    let l = native_hash([ux, uy, sx, sy]);
+    (native_hash([l, ux, uy]), native_hash([l, sx, sy]))
+}
+
+// X = w_u * P_u + w_s * P_s
+pub fn aggregated_key(user_key: &pallas::Affine, sequencer_key: &pallas::Affine) -> pallas::Affine {
+    let (w_u, w_s) = weights(user_key, sequencer_key);
+    (*user_key * base_to_scalar(w_u) + *sequencer_key * base_to_scalar(w_s)).to_affine()
+}
+
+// e = H(R || X || m || i)
+pub fn challenge(commit: &pallas::Affine, key: &pallas::Affine, message: pallas::Base, index: pallas::Base) -> pallas::Base {
+    let (rx, ry) = coordinates(commit);
+    let (xx, xy) = coordinates(key);
+    native_hash([rx, ry, xx, xy, message, index])
+}
+
+// both parties' rounds of the protocol, run in one place
+pub fn cosign(
+    rng: &mut (impl CryptoRng + RngCore),
+    user_secret: pallas::Scalar,
+    sequencer_secret: pallas::Scalar,
+    message: pallas::Base,
+    index: pallas::Base,
+) -> AggregatedSignature {
+    let g = fixed_bases::generator();
+    let user_key = (g * user_secret).to_affine();
+    let sequencer_key = (g * sequencer_secret).to_affine();
+    let (w_u, w_s) = weights(&user_key, &sequencer_key);
+
+    let r_u = pallas::Scalar::random(&mut *rng);
+    let r_s = pallas::Scalar::random(&mut *rng);
+    let commit = (g * (r_u + r_s)).to_affine();
+
+    let e = base_to_scalar(challenge(&commit, &aggregated_key(&user_key, &sequencer_key), message, index));
+    let s_u = r_u + user_secret * base_to_scalar(w_u) * e;
+    let s_s = r_s + sequencer_secret * base_to_scalar(w_s) * e;
+
+    AggregatedSignature { user_key, commit, response: s_u + s_s }
+}
+
+pub fn verify_aggregated(
+    sequencer_key: &pallas::Affine,
+    message: pallas::Base,
+    index: pallas::Base,
+    signature: &AggregatedSignature,
+) -> bool {
+    let key = aggregated_key(&signature.user_key, sequencer_key);
+    let e = base_to_scalar(challenge(&signature.commit, &key, message, index));
+    fixed_bases::generator() * signature.response == pallas::Point::from(signature.commit) + key * e
+}
+
+
+// == Circuit == //
+
+#[derive(Clone, Debug, Default)]
+pub struct SignatureWitness {
+    pub user_key: Value<pallas::Affine>,
+    pub message: Value<pallas::Base>,
+    pub commit: Value<pallas::Affine>,
+    pub response: Value<pallas::Scalar>,
+}
+
+impl SignatureWitness {
+    pub fn new(message: pallas::Base, signature: &AggregatedSignature) -> Self {
+        Self {
+            user_key: Value::known(signature.user_key),
+            message: Value::known(message),
+            commit: Value::known(signature.commit),
+            response: Value::known(signature.response),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EquivocationCircuit {
+    pub sequencer_key: Value<pallas::Affine>,
+    pub index: Value<pallas::Base>,
+    pub first: SignatureWitness,
+    pub second: SignatureWitness,
+}
+
+impl EquivocationCircuit {
+    pub fn instance(sequencer_key: &pallas::Affine, index: pallas::Base) -> Vec<pallas::Base> {
+        let (x, y) = coordinates(sequencer_key);
+        vec![x, y, index]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EquivocationConfig {
+    signature: SignatureConfig,
+    q_distinct: Selector,
+}
+
+impl EquivocationCircuit {
+    // checks one aggregated signature over (m, i) and returns the assigned message
+    fn constrain_aggregated(
+        &self,
+        config: &SignatureConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+        sequencer_key: &NonIdentityPoint<pallas::Affine, EccChip<SequencerFixedBases>>,
+        index: &AssignedCell<pallas::Base, pallas::Base>,
+        witness: &SignatureWitness,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        let ecc_chip = config.ecc_chip();
+        let user_key = NonIdentityPoint::new(ecc_chip.clone(), layouter.namespace(|| "P_u"), witness.user_key)?;
+        let (ux, uy) = (user_key.inner().x(), user_key.inner().y());
+        let (sx, sy) = (sequencer_key.inner().x(), sequencer_key.inner().y());
+
+        // l = H(P_u || P_s), w_u = H(l || P_u), w_s = H(l || P_s)
+        let l = hash(config, layouter.namespace(|| "l"), [ux.clone(), uy.clone(), sx.clone(), sy.clone()])?;
+        let w_u = hash(config, layouter.namespace(|| "w_u"), [l.clone(), ux, uy])?;
+        let w_s = hash(config, layouter.namespace(|| "w_s"), [l, sx, sy])?;
+
+        // X = w_u * P_u + w_s * P_s
+        let w_u = ScalarVar::from_base(ecc_chip.clone(), layouter.namespace(|| "w_u scalar"), &w_u)?;
+        let (weighted_user, _) = user_key.mul(layouter.namespace(|| "[w_u] P_u"), w_u)?;
+        let w_s = ScalarVar::from_base(ecc_chip.clone(), layouter.namespace(|| "w_s scalar"), &w_s)?;
+        let (weighted_sequencer, _) = sequencer_key.mul(layouter.namespace(|| "[w_s] P_s"), w_s)?;
+        let sum = weighted_user.add(layouter.namespace(|| "X"), &weighted_sequencer)?;
+
+        let key_value = witness
+            .user_key
+            .zip(self.sequencer_key)
+            .map(|(user_key, sequencer_key)| aggregated_key(&user_key, &sequencer_key));
+        let key = NonIdentityPoint::new(ecc_chip.clone(), layouter.namespace(|| "X witness"), key_value)?;
+        key.constrain_equal(layouter.namespace(|| "X == w_u * P_u + w_s * P_s"), &sum)?;
+
+        // e = H(R || X || m || i)
+        let message = load_private(layouter.namespace(|| "m"), config.advices[0], witness.message)?;
+        let commit = NonIdentityPoint::new(ecc_chip, layouter.namespace(|| "R"), witness.commit)?;
+        let challenge = hash(
+            config,
+            layouter.namespace(|| "e"),
+            [
+                commit.inner().x(),
+                commit.inner().y(),
+                key.inner().x(),
+                key.inner().y(),
+                message.clone(),
+                index.clone(),
+            ],
+        )?;
+
+        constrain_signature_equation(config, layouter.namespace(|| "s * G == R + e * X"), &commit, &key, &challenge, witness.response)?;
+        Ok(message)
+    }
+}
+
+impl Circuit<pallas::Base> for EquivocationCircuit {
+    type Config = EquivocationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        let signature = SignatureConfig::configure(meta);
+        let q_distinct = meta.selector();
+        let (a, b, inv) = (signature.advices[0], signature.advices[1], signature.advices[2]);
+
+        // m != m'  <=>  (m - m') has an inverse
+        meta.create_gate("m != m'", |meta| {
+            let q = meta.query_selector(q_distinct);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let inv = meta.query_advice(inv, Rotation::cur());
+            vec![q * ((a - b) * inv - Expression::Constant(pallas::Base::ONE))]
+        });
+
+        EquivocationConfig { signature, q_distinct }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<pallas::Base>) -> Result<(), Error> {
+        let signature = &config.signature;
+        signature.load_range_check_table(&mut layouter)?;
+
+        // the sequencer key P_s and the index i are public
+        let sequencer_key = NonIdentityPoint::new(signature.ecc_chip(), layouter.namespace(|| "P_s"), self.sequencer_key)?;
+        layouter.constrain_instance(sequencer_key.inner().x().cell(), signature.primary, SEQUENCER_X)?;
+        layouter.constrain_instance(sequencer_key.inner().y().cell(), signature.primary, SEQUENCER_Y)?;
+
+        let index = load_private(layouter.namespace(|| "i"), signature.advices[0], self.index)?;
+        layouter.constrain_instance(index.cell(), signature.primary, INDEX)?;
+
+        let first = self.constrain_aggregated(signature, layouter.namespace(|| "first"), &sequencer_key, &index, &self.first)?;
+        let second = self.constrain_aggregated(signature, layouter.namespace(|| "second"), &sequencer_key, &index, &self.second)?;
+
+        layouter.assign_region(
+            || "m != m'",
+            |mut region| {
+                config.q_distinct.enable(&mut region, 0)?;
+                let a = first.copy_advice(|| "m", &mut region, signature.advices[0], 0)?;
+                let b = second.copy_advice(|| "m'", &mut region, signature.advices[1], 0)?;
+                let inv = a.value().zip(b.value()).map(|(a, b)| (*a - *b).invert().unwrap_or(pallas::Base::ZERO));
+                region.assign_advice(|| "inv", signature.advices[2], 0, || inv)?;
+                Ok(())
+            },
+        )
+    }
+}
+
+
+#[test]
+pub fn test_equivocation_circuit() {
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let sequencer_secret = pallas::Scalar::random(OsRng);
+    let sequencer_key = (fixed_bases::generator() * sequencer_secret).to_affine();
+    let index = pallas::Base::from(42);
+
+    // two users get different txs committed to the same index
+    let m_1 = pallas::Base::random(OsRng);
+    let m_2 = pallas::Base::random(OsRng);
+    let sig_1 = cosign(&mut OsRng, pallas::Scalar::random(OsRng), sequencer_secret, m_1, index);
+    let sig_2 = cosign(&mut OsRng, pallas::Scalar::random(OsRng), sequencer_secret, m_2, index);
+    assert!(verify_aggregated(&sequencer_key, m_1, index, &sig_1));
+    assert!(verify_aggregated(&sequencer_key, m_2, index, &sig_2));
+
+    let circuit = EquivocationCircuit {
+        sequencer_key: Value::known(sequencer_key),
+        index: Value::known(index),
+        first: SignatureWitness::new(m_1, &sig_1),
+        second: SignatureWitness::new(m_2, &sig_2),
+    };
+    let prover = MockProver::run(K, &circuit, vec![EquivocationCircuit::instance(&sequencer_key, index)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // wrong public index or sequencer key
+    let prover = MockProver::run(K, &circuit, vec![EquivocationCircuit::instance(&sequencer_key, index + pallas::Base::ONE)]).unwrap();
+    assert!(prover.verify().is_err());
+    let other_key = (fixed_bases::generator() * pallas::Scalar::random(OsRng)).to_affine();
+    let prover = MockProver::run(K, &circuit, vec![EquivocationCircuit::instance(&other_key, index)]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // the same message twice is not an equivocation
+    let sig_3 = cosign(&mut OsRng, pallas::Scalar::random(OsRng), sequencer_secret, m_1, index);
+    let circuit = EquivocationCircuit { second: SignatureWitness::new(m_1, &sig_3), ..circuit };
+    let prover = MockProver::run(K, &circuit, vec![EquivocationCircuit::instance(&sequencer_key, index)]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // signatures for two different indices are not an equivocation
+    let sig_4 = cosign(&mut OsRng, pallas::Scalar::random(OsRng), sequencer_secret, m_2, index + pallas::Base::ONE);
+    let circuit = EquivocationCircuit { second: SignatureWitness::new(m_2, &sig_4), ..circuit };
+    let prover = MockProver::run(K, &circuit, vec![EquivocationCircuit::instance(&sequencer_key, index)]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -0,0 +1,103 @@
+use std::sync::OnceLock;
+
+use halo2_gadgets::ecc::chip::{
+    find_zs_and_us, BaseFieldElem, FixedPoint, FullScalar, ShortScalar, H, NUM_WINDOWS, NUM_WINDOWS_SHORT,
+};
+use halo2_gadgets::ecc::FixedPoints;
+use halo2_proofs::pasta::pallas;
+use halo2_pse::halo2curves::ff::PrimeField;
+use halo2_pse::halo2curves::group::{Curve, Group};
+
+// The only fixed base of the sequencer circuits is the Pallas generator G (s * G in the
+// signature equation). The window tables are searched once and cached for the process.
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SequencerFixedBases;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FullWidth;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BaseField;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Short;
+
+pub fn generator() -> pallas::Affine {
+    pallas::Point::generator().to_affine()
+}
+
+fn zs_and_us() -> &'static [(u64, [pallas::Base; H])] {
+    static ZS_AND_US: OnceLock<Vec<(u64, [pallas::Base; H])>> = OnceLock::new();
+    ZS_AND_US.get_or_init(|| find_zs_and_us(generator(), NUM_WINDOWS).unwrap())
+}
+
+fn zs_and_us_short() -> &'static [(u64, [pallas::Base; H])] {
+    static ZS_AND_US_SHORT: OnceLock<Vec<(u64, [pallas::Base; H])>> = OnceLock::new();
+    ZS_AND_US_SHORT.get_or_init(|| find_zs_and_us(generator(), NUM_WINDOWS_SHORT).unwrap())
+}
+
+fn u(zs_and_us: &[(u64, [pallas::Base; H])]) -> Vec<[[u8; 32]; H]> {
+    zs_and_us
+        .iter()
+        .map(|(_, us)| (*us).map(|u| u.to_repr()))
+        .collect()
+}
+
+fn z(zs_and_us: &[(u64, [pallas::Base; H])]) -> Vec<u64> {
+    zs_and_us.iter().map(|(z, _)| *z).collect()
+}
+
+impl FixedPoint<pallas::Affine> for FullWidth {
+    type FixedScalarKind = FullScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        generator()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        u(zs_and_us())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        z(zs_and_us())
+    }
+}
+
+impl FixedPoint<pallas::Affine> for BaseField {
+    type FixedScalarKind = BaseFieldElem;
+
+    fn generator(&self) -> pallas::Affine {
+        generator()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        u(zs_and_us())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        z(zs_and_us())
+    }
+}
+
+impl FixedPoint<pallas::Affine> for Short {
+    type FixedScalarKind = ShortScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        generator()
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        u(zs_and_us_short())
+    }
+
+    fn z(&self) -> Vec<u64> {
+        z(zs_and_us_short())
+    }
+}
+
+impl FixedPoints<pallas::Affine> for SequencerFixedBases {
+    type FullScalar = FullWidth;
+    type ShortScalar = Short;
+    type Base = BaseField;
+}
@@ -0,0 +1,211 @@
+use halo2::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector};
+use halo2::poly::Rotation;
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+
+use super::poseidon::{hash_rows, PoseidonChip, PoseidonConfig};
+use crate::merkle::InclusionProof;
+
+// Private inclusion of a tx in a sequenced batch : the prover knows a leaf and an
+// authentication path from the leaf at index i to the batch root.
+// One row per level :
+//   node | sibling | bit | left | right | acc_in | acc_out      (fixed : 2^h)
+//   bit is boolean, (left, right) = bit ? (sibling, node) : (node, sibling)
+//   acc_out = acc_in + bit * 2^h,  so that the last acc_out is sum_h bit_h * 2^h = i
+// then node' = H(left || right), same as merkle::hash_nodes.
+// Public inputs : the batch root and the index i.  Private : the leaf and the siblings.
+
+pub const ROOT: usize = 0;
+pub const INDEX: usize = 1;
+
+// rows used by one level : the level row plus one Poseidon hash of two inputs (68 rows)
+const ROWS_PER_LEVEL: usize = 1 + hash_rows(2);
+
+// smallest k fitting the leaf row and a path of 'depth' levels, with room for the blinding rows
+pub fn k(depth: usize) -> u32 {
+    let rows = 1 + depth * ROWS_PER_LEVEL + 16;
+    usize::BITS - (rows - 1).leading_zeros()
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleConfig<F> {
+    pub primary: Column<Instance>,
+    pub advices: [Column<Advice>; 7],
+    pub weight: Column<Fixed>,
+    pub q_level: Selector,
+    pub poseidon_config: PoseidonConfig<F>,
+}
+
+impl<F: FromUniformBytes<64> + Ord> MerkleConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let primary = meta.instance_column();
+        meta.enable_equality(primary);
+
+        let advices = [(); 7].map(|_| meta.advice_column());
+        for advice in advices.iter() {
+            meta.enable_equality(*advice);
+        }
+        let weight = meta.fixed_column();
+        let rc = [(); 5].map(|_| meta.fixed_column());
+
+        // shared fixed column for loading constants
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        let q_level = meta.selector();
+        meta.create_gate("merkle level", |meta| {
+            let q = meta.query_selector(q_level);
+            let node = meta.query_advice(advices[0], Rotation::cur());
+            let sibling = meta.query_advice(advices[1], Rotation::cur());
+            let bit = meta.query_advice(advices[2], Rotation::cur());
+            let left = meta.query_advice(advices[3], Rotation::cur());
+            let right = meta.query_advice(advices[4], Rotation::cur());
+            let acc_in = meta.query_advice(advices[5], Rotation::cur());
+            let acc_out = meta.query_advice(advices[6], Rotation::cur());
+            let weight = meta.query_any(weight, Rotation::cur());
+
+            let one = Expression::Constant(F::ONE);
+            vec![
+                q.clone() * bit.clone() * (one - bit.clone()),
+                q.clone() * (left - node.clone() - bit.clone() * (sibling.clone() - node.clone())),
+                q.clone() * (right - sibling.clone() - bit.clone() * (node - sibling)),
+                q * (acc_out - acc_in - bit * weight),
+            ]
+        });
+
+        let poseidon_config = PoseidonChip::configure(meta, advices[..5].try_into().unwrap(), rc);
+
+        Self { primary, advices, weight, q_level, poseidon_config }
+    }
+}
+
+
+// == Circuit == //
+
+#[derive(Clone, Debug)]
+pub struct MerkleCircuit<F, const DEPTH: usize> {
+    pub leaf: Value<F>,
+    pub index: Value<u64>,
+    pub siblings: Vec<Value<F>>,
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> Default for MerkleCircuit<F, DEPTH> {
+    fn default() -> Self {
+        Self { leaf: Value::unknown(), index: Value::unknown(), siblings: vec![Value::unknown(); DEPTH] }
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> MerkleCircuit<F, DEPTH> {
+    pub fn new(leaf: F, proof: &InclusionProof<F, DEPTH>) -> Self {
+        assert_eq!(proof.siblings.len(), DEPTH);
+        Self {
+            leaf: Value::known(leaf),
+            index: Value::known(proof.index),
+            siblings: proof.siblings.iter().map(|s| Value::known(*s)).collect(),
+        }
+    }
+
+    pub fn instance(root: F, index: u64) -> Vec<F> {
+        vec![root, F::from(index)]
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> Circuit<F> for MerkleCircuit<F, DEPTH> {
+    type Config = MerkleConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        MerkleConfig::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let poseidon = PoseidonChip::construct(config.poseidon_config.clone());
+
+        let mut node: AssignedCell<F, F> = layouter.assign_region(
+            || "leaf",
+            |mut region| region.assign_advice(|| "leaf", config.advices[0], 0, || self.leaf),
+        )?;
+        let mut acc: Option<AssignedCell<F, F>> = None;
+
+        for (h, sibling) in self.siblings.iter().enumerate() {
+            let bit = self.index.map(|i| F::from((i >> h) & 1));
+            let (left, right, acc_out) = layouter.assign_region(
+                || format!("level {}", h),
+                |mut region| {
+                    config.q_level.enable(&mut region, 0)?;
+                    region.assign_fixed(|| "2^h", config.weight, 0, || Value::known(F::from_u128(1 << h)))?;
+
+                    let node = node.copy_advice(|| "node", &mut region, config.advices[0], 0)?;
+                    region.assign_advice(|| "sibling", config.advices[1], 0, || *sibling)?;
+                    region.assign_advice(|| "bit", config.advices[2], 0, || bit)?;
+
+                    let swap = node.value().copied().zip(*sibling).zip(bit).map(|((node, sibling), bit)| {
+                        if bit == F::ONE { (sibling, node) } else { (node, sibling) }
+                    });
+                    let left = region.assign_advice(|| "left", config.advices[3], 0, || swap.map(|s| s.0))?;
+                    let right = region.assign_advice(|| "right", config.advices[4], 0, || swap.map(|s| s.1))?;
+
+                    let acc_in = match &acc {
+                        Some(acc) => acc.copy_advice(|| "acc_in", &mut region, config.advices[5], 0)?,
+                        None => region.assign_advice_from_constant(|| "acc_in", config.advices[5], 0, F::ZERO)?,
+                    };
+                    let acc_out = acc_in.value().copied().zip(bit).map(|(acc, bit)| acc + bit * F::from_u128(1 << h));
+                    let acc_out = region.assign_advice(|| "acc_out", config.advices[6], 0, || acc_out)?;
+                    Ok((left, right, acc_out))
+                },
+            )?;
+
+            node = poseidon.hash(layouter.namespace(|| format!("hash {}", h)), &[left, right])?;
+            acc = Some(acc_out);
+        }
+
+        layouter.constrain_instance(node.cell(), config.primary, ROOT)?;
+        match acc {
+            Some(acc) => layouter.constrain_instance(acc.cell(), config.primary, INDEX),
+            // a tree of depth 0 only holds the leaf at index 0
+            None => {
+                let zero = layouter.assign_region(
+                    || "index 0",
+                    |mut region| region.assign_advice_from_constant(|| "index", config.advices[5], 0, F::ZERO),
+                )?;
+                layouter.constrain_instance(zero.cell(), config.primary, INDEX)
+            }
+        }
+    }
+}
+
+
+// the tests run against each backend's copy, over its field (see circuits/mod.rs)
+#[cfg(test)]
+fn check_merkle_circuit<F: FromUniformBytes<64> + Ord>() {
+    use halo2::dev::MockProver;
+    use rand::rngs::OsRng;
+    use crate::merkle::MerkleTree;
+
+    const DEPTH: usize = 8;
+    let mut tree = MerkleTree::<F, DEPTH>::new();
+    let leaves = (0..37).map(|_| F::random(OsRng)).collect::<Vec<F>>();
+    leaves.iter().for_each(|leaf| { tree.append(*leaf); });
+    let root = tree.root();
+
+    for index in [0, 1, 22, 36] {
+        let proof = tree.prove(index).unwrap();
+        let circuit = MerkleCircuit::<_, DEPTH>::new(leaves[index as usize], &proof);
+        let prover = MockProver::run(k(DEPTH), &circuit, vec![MerkleCircuit::<_, DEPTH>::instance(root, index)]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the path is bound to its position : the same leaf cannot be claimed at another index
+        let prover = MockProver::run(k(DEPTH), &circuit, vec![MerkleCircuit::<_, DEPTH>::instance(root, index + 1)]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // a leaf outside the batch does not reach the root
+    let proof = tree.prove(5).unwrap();
+    let circuit = MerkleCircuit::<_, DEPTH>::new(F::random(OsRng), &proof);
+    let prover = MockProver::run(k(DEPTH), &circuit, vec![MerkleCircuit::<_, DEPTH>::instance(root, 5)]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -0,0 +1,61 @@
+// Halo2 circuits over the Pasta cycle (zcash halo2_proofs 0.3 + halo2_gadgets 0.3).
+// The ECC chip only exists for Pallas, so the in-circuit versions of the sequencer
+// signatures live on Pallas : base field pallas::Base, scalar field pallas::Scalar.
+
+pub mod fixed_bases;
+
+// Circuits using only the core halo2 API are written once against a 'halo2' alias and
+// included again by the KZG backend with halo2_pse (backend::kzg::circuits). poseidon.rs and
+// merkle.rs are not modules of their own, the alias comes from where they are included, and
+// so do their tests : each copy is tested over the fields of its backend.
+pub mod poseidon {
+    use halo2_proofs as halo2;
+    include!("poseidon.rs");
+
+    #[test]
+    pub fn test_poseidon_chip_pallas() {
+        differential::<halo2_proofs::pasta::pallas::Base>();
+    }
+}
+pub mod merkle {
+    use halo2_proofs as halo2;
+    include!("merkle.rs");
+
+    #[test]
+    pub fn test_merkle_circuit_pallas() {
+        check_merkle_circuit::<halo2_proofs::pasta::pallas::Base>();
+    }
+
+    // the layout is the same under both backends
+    #[test]
+    pub fn test_merkle_circuit_rows() {
+        use halo2_proofs::dev::MockProver;
+        use halo2_proofs::pasta::pallas;
+        use halo2_pse::halo2curves::ff::Field;
+        use rand::rngs::OsRng;
+        use crate::merkle::MerkleTree;
+
+        // 59 levels are the most 2^12 rows hold, 60 levels need 60 * 69 + 1 rows
+        const DEPTH: usize = 60;
+        assert_eq!(ROWS_PER_LEVEL, 69);
+        assert_eq!(k(DEPTH - 1), 12);
+        assert_eq!(k(DEPTH), 13);
+
+        let mut tree = MerkleTree::<pallas::Base, DEPTH>::new();
+        let leaf = pallas::Base::random(OsRng);
+        tree.append(leaf);
+        let proof = tree.prove(0).unwrap();
+        let circuit = MerkleCircuit::<_, DEPTH>::new(leaf, &proof);
+        let instance = vec![MerkleCircuit::<_, DEPTH>::instance(tree.root(), 0)];
+
+        let prover = MockProver::run(k(DEPTH), &circuit, instance.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert!(MockProver::run(k(DEPTH) - 1, &circuit, instance).is_err());
+    }
+}
+
+// The sequencer signature (schnorr) and equivocation circuits use the ECC chip of
+// halo2_gadgets, which only exists for zcash halo2 over Pallas : they are IPA-only, the KZG
+// backend proves the Poseidon and Merkle circuits alone.
+pub mod schnorr;
+pub mod equivocation;
@@ -0,0 +1,302 @@
+use std::marker::PhantomData;
+
+use halo2_gadgets::poseidon::primitives::{generate_constants, Mds, Spec};
+use halo2::circuit::{AssignedCell, Layouter, Value};
+use halo2::plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector};
+use halo2::poly::Rotation;
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+
+use crate::halo2::{POSEIDON_RATE as RATE, POSEIDON_R_F as R_F, POSEIDON_R_P as R_P, POSEIDON_T as T};
+
+// Poseidon chip that hashes exactly like the native crate::halo2::poseidon_hash (PSE poseidon crate) :
+//  - same width, rate and round counts (T = 5, RATE = 4, R_F = 8, R_P = 57), x^5 S-box
+//  - state starts as [2^64, 0, 0, 0, 0], inputs are added to state[1..] one RATE chunk at a time
+//  - the last chunk is padded with a single 1 (an extra [1] chunk when the input fills every chunk)
+//  - the digest is state[1]
+// One row per round : the gates apply ARK, S-box and MDS between two consecutive rows.
+// A fixed column must be enabled for constants (initial state and padding).
+// Only the core halo2 API is used, so the chip builds under every backend (see backend).
+
+// Round constants and MDS matrix, generated with the Grain LFSR like the PSE crate does
+#[derive(Debug, Clone, Copy)]
+pub struct PoseidonSpec;
+
+impl<F: FromUniformBytes<64> + Ord> Spec<F, T, RATE> for PoseidonSpec {
+    fn full_rounds() -> usize {
+        R_F
+    }
+
+    fn partial_rounds() -> usize {
+        R_P
+    }
+
+    fn sbox(val: F) -> F {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> (Vec<[F; T]>, Mds<F, T>, Mds<F, T>) {
+        generate_constants::<F, Self, T, RATE>()
+    }
+}
+
+// rows of one hash region : per chunk a state row, a chunk row and one row per round, then the digest row
+pub const fn hash_rows(n_inputs: usize) -> usize {
+    (n_inputs / RATE + 1) * (2 + R_F + R_P) + 1
+}
+
+fn is_full_round(round: usize) -> bool {
+    round < R_F / 2 || round >= R_F / 2 + R_P
+}
+
+// one round of the permutation, the native counterpart of the round gates
+fn round<F: FromUniformBytes<64> + Ord>(state: [F; T], rc: &[F; T], mds: &Mds<F, T>, full: bool) -> [F; T] {
+    let mut sboxed = [F::ZERO; T];
+    for j in 0..T {
+        sboxed[j] = state[j] + rc[j];
+        if full || j == 0 {
+            sboxed[j] = <PoseidonSpec as Spec<F, T, RATE>>::sbox(sboxed[j]);
+        }
+    }
+    let mut next = [F::ZERO; T];
+    for (i, row) in mds.iter().enumerate() {
+        next[i] = row.iter().zip(sboxed.iter()).fold(F::ZERO, |acc, (m, x)| acc + *m * x);
+    }
+    next
+}
+
+#[derive(Clone, Debug)]
+pub struct PoseidonConfig<F> {
+    state: [Column<Advice>; T],
+    rc: [Column<Fixed>; T],
+    q_full: Selector,
+    q_partial: Selector,
+    q_absorb: Selector,
+    round_constants: Vec<[F; T]>,
+    mds: Mds<F, T>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PoseidonChip<F> {
+    config: PoseidonConfig<F>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FromUniformBytes<64> + Ord> PoseidonChip<F> {
+    pub fn construct(config: PoseidonConfig<F>) -> Self {
+        Self { config, _marker: PhantomData }
+    }
+
+    pub fn configure(meta: &mut ConstraintSystem<F>, state: [Column<Advice>; T], rc: [Column<Fixed>; T]) -> PoseidonConfig<F> {
+        for column in state.iter() {
+            meta.enable_equality(*column);
+        }
+        let q_full = meta.selector();
+        let q_partial = meta.selector();
+        let q_absorb = meta.selector();
+        let (round_constants, mds, _) = <PoseidonSpec as Spec<F, T, RATE>>::constants();
+
+        let pow_5 = |x: Expression<F>| x.clone() * x.clone() * x.clone() * x.clone() * x;
+
+        // next_i = sum_j M_ij * (cur_j + rc_j)^5
+        meta.create_gate("poseidon full round", |meta| {
+            let q = meta.query_selector(q_full);
+            let sboxed = (0..T)
+                .map(|j| pow_5(meta.query_advice(state[j], Rotation::cur()) + meta.query_any(rc[j], Rotation::cur())))
+                .collect::<Vec<_>>();
+            (0..T)
+                .map(|i| {
+                    let next = meta.query_advice(state[i], Rotation::next());
+                    let mixed = (0..T).fold(Expression::Constant(F::ZERO), |acc, j| {
+                        acc + Expression::Constant(mds[i][j]) * sboxed[j].clone()
+                    });
+                    q.clone() * (next - mixed)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // next_i = M_i0 * (cur_0 + rc_0)^5 + sum_{j > 0} M_ij * (cur_j + rc_j)
+        meta.create_gate("poseidon partial round", |meta| {
+            let q = meta.query_selector(q_partial);
+            let sboxed = (0..T)
+                .map(|j| {
+                    let x = meta.query_advice(state[j], Rotation::cur()) + meta.query_any(rc[j], Rotation::cur());
+                    if j == 0 { pow_5(x) } else { x }
+                })
+                .collect::<Vec<_>>();
+            (0..T)
+                .map(|i| {
+                    let next = meta.query_advice(state[i], Rotation::next());
+                    let mixed = (0..T).fold(Expression::Constant(F::ZERO), |acc, j| {
+                        acc + Expression::Constant(mds[i][j]) * sboxed[j].clone()
+                    });
+                    q.clone() * (next - mixed)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // state (cur) + chunk (next) = absorbed state (next + 1)
+        meta.create_gate("poseidon absorb", |meta| {
+            let q = meta.query_selector(q_absorb);
+            (0..T)
+                .map(|i| {
+                    let cur = meta.query_advice(state[i], Rotation::cur());
+                    let input = meta.query_advice(state[i], Rotation::next());
+                    let absorbed = meta.query_advice(state[i], Rotation(2));
+                    q.clone() * (absorbed - cur - input)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        PoseidonConfig { state, rc, q_full, q_partial, q_absorb, round_constants, mds }
+    }
+
+    // H(inputs), equal to poseidon_hash(inputs) for the assigned values
+    pub fn hash(&self, mut layouter: impl Layouter<F>, inputs: &[AssignedCell<F, F>]) -> Result<AssignedCell<F, F>, Error> {
+        let config = &self.config;
+
+        // full chunks, then the remaining inputs followed by the padding 1
+        let full_chunks = inputs.len() / RATE;
+        let mut chunks = inputs.chunks(RATE).take(full_chunks).map(|c| (c, false)).collect::<Vec<_>>();
+        chunks.push((&inputs[full_chunks * RATE..], true));
+
+        layouter.assign_region(
+            || "poseidon hash",
+            |mut region| {
+                let mut initial = [F::ZERO; T];
+                initial[0] = F::from_u128(1 << 64);
+                let mut state = Value::known(initial);
+                let mut cells = Vec::with_capacity(T);
+                for (i, value) in initial.iter().enumerate() {
+                    cells.push(region.assign_advice_from_constant(|| "initial state", config.state[i], 0, *value)?);
+                }
+
+                let mut offset = 0;
+                for (chunk, padded) in chunks.iter() {
+                    // absorb : row offset holds the state, row offset + 1 the chunk
+                    config.q_absorb.enable(&mut region, offset)?;
+                    let mut chunk_value = Value::known([F::ZERO; T]);
+                    region.assign_advice_from_constant(|| "capacity", config.state[0], offset + 1, F::ZERO)?;
+                    for j in 0..RATE {
+                        let column = config.state[j + 1];
+                        let value = if j < chunk.len() {
+                            chunk[j].copy_advice(|| "input", &mut region, column, offset + 1)?;
+                            chunk[j].value().copied()
+                        } else {
+                            let padding = if *padded && j == chunk.len() { F::ONE } else { F::ZERO };
+                            region.assign_advice_from_constant(|| "padding", column, offset + 1, padding)?;
+                            Value::known(padding)
+                        };
+                        chunk_value = chunk_value.zip(value).map(|(mut c, v)| { c[j + 1] = v; c });
+                    }
+                    state = state.zip(chunk_value).map(|(s, c)| {
+                        let mut absorbed = s;
+                        absorbed.iter_mut().zip(c.iter()).for_each(|(a, c)| *a += c);
+                        absorbed
+                    });
+                    offset += 2;
+                    cells = self.assign_state(&mut region, offset, state)?;
+
+                    // permutation : one row per round
+                    for (r, rc) in config.round_constants.iter().enumerate() {
+                        let full = is_full_round(r);
+                        if full {
+                            config.q_full.enable(&mut region, offset)?;
+                        } else {
+                            config.q_partial.enable(&mut region, offset)?;
+                        }
+                        for j in 0..T {
+                            region.assign_fixed(|| "round constant", config.rc[j], offset, || Value::known(rc[j]))?;
+                        }
+                        state = state.map(|s| round(s, rc, &config.mds, full));
+                        offset += 1;
+                        cells = self.assign_state(&mut region, offset, state)?;
+                    }
+                }
+
+                Ok(cells[1].clone())
+            },
+        )
+    }
+
+    fn assign_state(
+        &self,
+        region: &mut halo2::circuit::Region<'_, F>,
+        offset: usize,
+        state: Value<[F; T]>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        (0..T)
+            .map(|i| region.assign_advice(|| "state", self.config.state[i], offset, || state.map(|s| s[i])))
+            .collect()
+    }
+}
+
+
+// == Differential test harness == //
+
+// hashes 'inputs' in circuit and exposes the digest as the only public input
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+struct HashCircuit<F> {
+    inputs: Vec<Value<F>>,
+}
+
+#[cfg(test)]
+impl<F: FromUniformBytes<64> + Ord> halo2::plonk::Circuit<F> for HashCircuit<F> {
+    type Config = (PoseidonConfig<F>, Column<Advice>, Column<halo2::plonk::Instance>);
+    type FloorPlanner = halo2::circuit::SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self { inputs: vec![Value::unknown(); self.inputs.len()] }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let state = [(); T].map(|_| meta.advice_column());
+        let rc = [(); T].map(|_| meta.fixed_column());
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        (PoseidonChip::configure(meta, state, rc), state[0], instance)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let (poseidon_config, advice, instance) = config;
+        let inputs = layouter.assign_region(
+            || "inputs",
+            |mut region| {
+                self.inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(row, value)| region.assign_advice(|| "input", advice, row, || *value))
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )?;
+        let digest = PoseidonChip::construct(poseidon_config).hash(layouter.namespace(|| "hash"), &inputs)?;
+        layouter.constrain_instance(digest.cell(), instance, 0)
+    }
+}
+
+// random input vectors of every length up to three full chunks, so that both the
+// partially filled and the exactly filled last chunk are exercised
+#[cfg(test)]
+fn differential<F: FromUniformBytes<64> + Ord>() {
+    use halo2::dev::MockProver;
+    use rand::rngs::OsRng;
+    use crate::halo2::poseidon_hash;
+
+    for len in 0..=12 {
+        let inputs = (0..len).map(|_| F::random(OsRng)).collect::<Vec<F>>();
+        let digest = poseidon_hash(inputs.clone());
+
+        let circuit = HashCircuit { inputs: inputs.iter().map(|x| Value::known(*x)).collect() };
+        let prover = MockProver::run(9, &circuit, vec![vec![digest]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()), "digest mismatch for {} inputs", len);
+
+        let prover = MockProver::run(9, &circuit, vec![vec![digest + F::ONE]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use halo2_gadgets::ecc::chip::{EccChip, EccConfig};
+use halo2_gadgets::ecc::{FixedPoint, NonIdentityPoint, Point, ScalarFixed, ScalarVar};
+use halo2_gadgets::sinsemilla::primitives as sinsemilla;
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::pasta::pallas;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, TableColumn};
+use halo2_pse::halo2curves::ff::{Field, PrimeField};
+use halo2_pse::halo2curves::group::Curve;
+use rand::{CryptoRng, RngCore};
+
+use super::fixed_bases::{self, FullWidth, SequencerFixedBases};
+use super::poseidon::{PoseidonChip, PoseidonConfig};
+use crate::halo2::poseidon_hash;
+
+// Circuit proving knowledge of a valid Schnorr signature (R, s) from P over m.
+// The witness layout follows SchnorrPublic::verify :
+//   e = H(R.x || R.y || m)
+//   s * G == R + e * P
+// Public inputs : P.x, P.y, m.  Private : R, s.
+
+pub const K: u32 = 11;
+
+pub const PK_X: usize = 0;
+pub const PK_Y: usize = 1;
+pub const MESSAGE: usize = 2;
+
+
+// == Native signatures on Pallas (witness generation) == //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PallasSignature {
+    pub commit: pallas::Affine,
+    pub response: pallas::Scalar,
+}
+
+// Base to Scalar : no reduction needed, the Pallas base field is smaller than the scalar field
+pub fn base_to_scalar(x: pallas::Base) -> pallas::Scalar {
+    pallas::Scalar::from_repr(x.to_repr()).unwrap()
+}
+
+pub fn coordinates(point: &pallas::Affine) -> (pallas::Base, pallas::Base) {
+    let coords = point.coordinates().unwrap();
+    (*coords.x(), *coords.y())
+}
+
+// H(inputs) with the native poseidon_hash, the counterpart of 'hash'
+pub fn native_hash<const L: usize>(inputs: [pallas::Base; L]) -> pallas::Base {
+    poseidon_hash(inputs.to_vec())
+}
+
+pub fn challenge(commit: &pallas::Affine, message: pallas::Base) -> pallas::Base {
+    let (rx, ry) = coordinates(commit);
+    native_hash([rx, ry, message])
+}
+
+pub fn public_key(secret: pallas::Scalar) -> pallas::Affine {
+    (fixed_bases::generator() * secret).to_affine()
+}
+
+pub fn sign(rng: &mut (impl CryptoRng + RngCore), secret: pallas::Scalar, message: pallas::Base) -> PallasSignature {
+    let mask = pallas::Scalar::random(rng);
+    let commit = (fixed_bases::generator() * mask).to_affine();
+    let response = mask + base_to_scalar(challenge(&commit, message)) * secret;
+    PallasSignature { commit, response }
+}
+
+pub fn verify(public_key: &pallas::Affine, message: pallas::Base, signature: &PallasSignature) -> bool {
+    let e = base_to_scalar(challenge(&signature.commit, message));
+    fixed_bases::generator() * signature.response - *public_key * e == pallas::Point::from(signature.commit)
+}
+
+
+// == Configuration shared by the signature circuits == //
+
+#[derive(Clone, Debug)]
+pub struct SignatureConfig {
+    pub primary: Column<Instance>,
+    pub advices: [Column<Advice>; 10],
+    pub table_idx: TableColumn,
+    pub ecc_config: EccConfig<SequencerFixedBases>,
+    pub poseidon_config: PoseidonConfig<pallas::Base>,
+}
+
+impl SignatureConfig {
+    pub fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self {
+        let primary = meta.instance_column();
+        meta.enable_equality(primary);
+
+        let advices = [(); 10].map(|_| meta.advice_column());
+        for advice in advices.iter() {
+            meta.enable_equality(*advice);
+        }
+
+        let table_idx = meta.lookup_table_column();
+        let lagrange_coeffs = [(); 8].map(|_| meta.fixed_column());
+
+        // shared fixed column for loading constants
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        let range_check = LookupRangeCheckConfig::configure(meta, advices[9], table_idx);
+        let ecc_config = EccChip::<SequencerFixedBases>::configure(meta, advices, lagrange_coeffs, range_check);
+
+        // the Poseidon rounds share columns with the ECC chip, they never sit in the same region
+        let poseidon_config = PoseidonChip::configure(
+            meta,
+            advices[5..10].try_into().unwrap(),
+            lagrange_coeffs[3..8].try_into().unwrap(),
+        );
+
+        Self { primary, advices, table_idx, ecc_config, poseidon_config }
+    }
+
+    pub fn ecc_chip(&self) -> EccChip<SequencerFixedBases> {
+        EccChip::construct(self.ecc_config.clone())
+    }
+
+    pub fn poseidon_chip(&self) -> PoseidonChip<pallas::Base> {
+        PoseidonChip::construct(self.poseidon_config.clone())
+    }
+
+    // 10-bit lookup table used by the ECC range checks
+    pub fn load_range_check_table(&self, layouter: &mut impl Layouter<pallas::Base>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "table_idx",
+            |mut table| {
+                for index in 0..(1 << sinsemilla::K) {
+                    table.assign_cell(|| "table_idx", self.table_idx, index, || Value::known(pallas::Base::from(index as u64)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+pub fn load_private(
+    mut layouter: impl Layouter<pallas::Base>,
+    column: Column<Advice>,
+    value: Value<pallas::Base>,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    layouter.assign_region(
+        || "load private",
+        |mut region| region.assign_advice(|| "private input", column, 0, || value),
+    )
+}
+
+// H(inputs), hashes like the native poseidon_hash
+pub fn hash<const L: usize>(
+    config: &SignatureConfig,
+    mut layouter: impl Layouter<pallas::Base>,
+    inputs: [AssignedCell<pallas::Base, pallas::Base>; L],
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    config.poseidon_chip().hash(layouter.namespace(|| "poseidon hash"), &inputs)
+}
+
+// s * G == R + e * K
+pub fn constrain_signature_equation(
+    config: &SignatureConfig,
+    mut layouter: impl Layouter<pallas::Base>,
+    commit: &NonIdentityPoint<pallas::Affine, EccChip<SequencerFixedBases>>,
+    key: &NonIdentityPoint<pallas::Affine, EccChip<SequencerFixedBases>>,
+    challenge: &AssignedCell<pallas::Base, pallas::Base>,
+    response: Value<pallas::Scalar>,
+) -> Result<(), Error> {
+    let ecc_chip = config.ecc_chip();
+
+    // s * G
+    let response = ScalarFixed::new(ecc_chip.clone(), layouter.namespace(|| "s"), response)?;
+    let generator = FixedPoint::from_inner(ecc_chip.clone(), FullWidth);
+    let (left, _) = generator.mul(layouter.namespace(|| "[s] G"), response)?;
+
+    // R + e * K
+    let challenge = ScalarVar::from_base(ecc_chip, layouter.namespace(|| "e"), challenge)?;
+    let (e_key, _) = key.mul(layouter.namespace(|| "[e] K"), challenge)?;
+    let right: Point<_, _> = commit.add(layouter.namespace(|| "R + [e] K"), &e_key)?;
+
+    left.constrain_equal(layouter.namespace(|| "s * G == R + e * K"), &right)
+}
+
+
+// == Circuit == //
+
+#[derive(Clone, Debug, Default)]
+pub struct SchnorrCircuit {
+    pub public_key: Value<pallas::Affine>,
+    pub message: Value<pallas::Base>,
+    pub commit: Value<pallas::Affine>,
+    pub response: Value<pallas::Scalar>,
+}
+
+impl SchnorrCircuit {
+    pub fn new(public_key: pallas::Affine, message: pallas::Base, signature: PallasSignature) -> Self {
+        Self {
+            public_key: Value::known(public_key),
+            message: Value::known(message),
+            commit: Value::known(signature.commit),
+            response: Value::known(signature.response),
+        }
+    }
+
+    pub fn instance(public_key: &pallas::Affine, message: pallas::Base) -> Vec<pallas::Base> {
+        let (x, y) = coordinates(public_key);
+        vec![x, y, message]
+    }
+}
+
+impl Circuit<pallas::Base> for SchnorrCircuit {
+    type Config = SignatureConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        SignatureConfig::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<pallas::Base>) -> Result<(), Error> {
+        config.load_range_check_table(&mut layouter)?;
+        let ecc_chip = config.ecc_chip();
+
+        // public key P and message m are public
+        let public_key = NonIdentityPoint::new(ecc_chip.clone(), layouter.namespace(|| "P"), self.public_key)?;
+        layouter.constrain_instance(public_key.inner().x().cell(), config.primary, PK_X)?;
+        layouter.constrain_instance(public_key.inner().y().cell(), config.primary, PK_Y)?;
+
+        let message = load_private(layouter.namespace(|| "m"), config.advices[0], self.message)?;
+        layouter.constrain_instance(message.cell(), config.primary, MESSAGE)?;
+
+        // e = H(R.x || R.y || m)
+        let commit = NonIdentityPoint::new(ecc_chip, layouter.namespace(|| "R"), self.commit)?;
+        let challenge = hash(
+            &config,
+            layouter.namespace(|| "e"),
+            [commit.inner().x(), commit.inner().y(), message],
+        )?;
+
+        constrain_signature_equation(&config, layouter.namespace(|| "verify"), &commit, &public_key, &challenge, self.response)
+    }
+}
+
+
+#[test]
+pub fn test_schnorr_circuit_mock() {
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let secret = pallas::Scalar::random(OsRng);
+    let public_key = public_key(secret);
+    let message = pallas::Base::random(OsRng);
+    let signature = sign(&mut OsRng, secret, message);
+    assert!(verify(&public_key, message, &signature));
+
+    let circuit = SchnorrCircuit::new(public_key, message, signature);
+    let instance = SchnorrCircuit::instance(&public_key, message);
+    let prover = MockProver::run(K, &circuit, vec![instance]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // the same signature does not verify for another message
+    let instance = SchnorrCircuit::instance(&public_key, message + pallas::Base::ONE);
+    let prover = MockProver::run(K, &circuit, vec![instance]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // a forged response is rejected
+    let forged = PallasSignature { response: signature.response + pallas::Scalar::ONE, ..signature };
+    let circuit = SchnorrCircuit::new(public_key, message, forged);
+    let prover = MockProver::run(K, &circuit, vec![SchnorrCircuit::instance(&public_key, message)]).unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+pub fn test_schnorr_circuit_proof() {
+    use halo2_proofs::pasta::vesta;
+    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier};
+    use halo2_proofs::poly::commitment::Params;
+    use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
+    use rand::rngs::OsRng;
+
+    let secret = pallas::Scalar::random(OsRng);
+    let public_key = public_key(secret);
+    let message = pallas::Base::random(OsRng);
+    let signature = sign(&mut OsRng, secret, message);
+    let circuit = SchnorrCircuit::new(public_key, message, signature);
+    let instance = SchnorrCircuit::instance(&public_key, message);
+
+    let params = Params::<vesta::Affine>::new(K);
+    let vk = keygen_vk(&params, &SchnorrCircuit::default()).unwrap();
+    let pk = keygen_pk(&params, vk, &SchnorrCircuit::default()).unwrap();
+
+    let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[&instance]], OsRng, &mut transcript).unwrap();
+    let proof = transcript.finalize();
+
+    let strategy = SingleVerifier::new(&params);
+    let mut transcript = Blake2bRead::<_, vesta::Affine, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof(&params, pk.get_vk(), strategy, &[&[&instance]], &mut transcript).is_ok());
+
+    // the proof is bound to the public inputs
+    let wrong = SchnorrCircuit::instance(&public_key, message + pallas::Base::ONE);
+    let strategy = SingleVerifier::new(&params);
+    let mut transcript = Blake2bRead::<_, vesta::Affine, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof(&params, pk.get_vk(), strategy, &[&[&wrong]], &mut transcript).is_err());
+}
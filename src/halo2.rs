@@ -1,13 +1,13 @@
 use std::{time::Instant, mem::size_of, ops::Mul};
-use halo2_proofs::arithmetic::{Field, CurveAffine};
+use halo2_pse::halo2curves::{ff::Field, CurveAffine};
 use rand::{thread_rng, rngs::OsRng};
 use rayon::prelude::*;
 
-use halo2curves::{
+use halo2_pse::halo2curves::{
     bn256::{Fr as Scalar, G1Affine as Affine, G1 as Point}, ff::FromUniformBytes, group::{Group, GroupEncoding},
 };
-use halo2curves::ff::PrimeField;
-use halo2curves::group::Curve;
+use halo2_pse::halo2curves::ff::PrimeField;
+use halo2_pse::halo2curves::group::Curve;
 
 use halo2_pse::arithmetic::best_multiexp;
 
@@ -103,24 +103,30 @@ pub fn gen_key_pair<C: CurveAffine>() -> C {
 
     // generate a key pair
     let sk = <C as CurveAffine>::ScalarExt::random(OsRng);
-    let public_key = (g * sk).to_affine();  // to_affine belongs to "use halo2curves::group::Curve"
+    let public_key = (g * sk).to_affine();  // to_affine belongs to "use halo2_pse::halo2curves::group::Curve"
 
     public_key
 }
 
 
 
-// FromUnitformBytes from halo2curves::ff::FromUniformBytes
+// Poseidon parameters, shared with the in-circuit chip (circuits::poseidon)
+pub const POSEIDON_T: usize = 5;
+pub const POSEIDON_RATE: usize = 4;
+pub const POSEIDON_R_F: usize = 8;
+pub const POSEIDON_R_P: usize = 57;
+
+// FromUnitformBytes from halo2_pse::halo2curves::ff::FromUniformBytes
 pub fn poseidon_hash<N: FromUniformBytes<64> + Ord>(inputs: Vec<N>)
         -> N {
-    // use halo2curves::bn256::Fr as BnScalar;
+    // use halo2_pse::halo2curves::bn256::Fr as BnScalar;
     use poseidon::Poseidon;
 
     // constants
-    const T: usize = 5;
-    const RATE: usize = 4;
-    const R_F: usize = 8;
-    const R_P: usize = 57;
+    const T: usize = POSEIDON_T;
+    const RATE: usize = POSEIDON_RATE;
+    const R_F: usize = POSEIDON_R_F;
+    const R_P: usize = POSEIDON_R_P;
 
     let mut poseidon = Poseidon::<N, T, RATE>::new(R_F, R_P);
     let number_of_permutation = 5;
@@ -140,8 +146,8 @@ pub fn poseidon_hash<N: FromUniformBytes<64> + Ord>(inputs: Vec<N>)
 #[test]
 pub fn test_gen_key() {
 
-    use halo2curves::bn256::Fr as BnScalar;
-    use halo2curves::bn256::G1Affine as G1Affine;
+    use halo2_pse::halo2curves::bn256::Fr as BnScalar;
+    use halo2_pse::halo2curves::bn256::G1Affine as G1Affine;
 
 
     let keys = gen_key_pair::<G1Affine>();
@@ -153,7 +159,7 @@ pub fn test_gen_key() {
 #[test]
 pub fn test_poseidon_hash() {
     
-    use halo2curves::bn256::Fr as BnScalar;
+    use halo2_pse::halo2curves::bn256::Fr as BnScalar;
     let inputs = (0..2).map(|_| BnScalar::random(OsRng))
                                             .collect::<Vec<BnScalar>>();
     
@@ -166,8 +172,8 @@ pub fn test_poseidon_hash() {
 #[test]
 pub fn test_conversion() {
 
-    use halo2curves::bn256::{Fr, Fq};
-    use halo2curves::bn256::G1 as Group;
+    use halo2_pse::halo2curves::bn256::{Fr, Fq};
+    use halo2_pse::halo2curves::bn256::G1 as Group;
 
     let fr_val = Fr::random(OsRng);
     let fq_val = Fq::random(OsRng);
@@ -188,9 +194,9 @@ pub fn test_conversion() {
 pub fn test_schnorr_sequencer() 
 {
 
-    use halo2curves::bn256::{Fr, Fq};
-    use halo2curves::bn256::G1 as Group;
-    use halo2curves::CurveExt;
+    use halo2_pse::halo2curves::bn256::{Fr, Fq};
+    use halo2_pse::halo2curves::bn256::G1 as Group;
+    use halo2_pse::halo2curves::CurveExt;
 
     // transaction(message) and its hash!!
     let message_plain = Group::random(OsRng).to_affine();    
@@ -322,12 +328,12 @@ pub fn test_schnorr_sequencer()
 
 #[test]
 pub fn test_curve_feature() {
-    use halo2curves::bn256::G1;
-    use halo2curves::CurveExt;
+    use halo2_pse::halo2curves::bn256::G1;
+    use halo2_pse::halo2curves::CurveExt;
 
     let projective_point = G1::random(OsRng);
     // let affine_point: G::AffineExt = projective_point.into();
-    let affine_point: <halo2curves::bn256::G1 as CurveExt>::AffineExt = projective_point.into();
+    let affine_point: <halo2_pse::halo2curves::bn256::G1 as CurveExt>::AffineExt = projective_point.into();
 
     // Converts this element into its byte encoding. This may or may not support encoding the identity.
     let projective_repr = projective_point.to_bytes();
@@ -341,22 +347,22 @@ pub fn test_curve_feature() {
 #[test]
 pub fn test_multiplication() {
 
-    use halo2curves::bn256::G1 as G;
-    use halo2curves::CurveExt;
+    use halo2_pse::halo2curves::bn256::G1 as G;
+    use halo2_pse::halo2curves::CurveExt;
 
-    let s1 = <halo2curves::bn256::G1 as CurveExt>::ScalarExt::random(OsRng);
+    let s1 = <halo2_pse::halo2curves::bn256::G1 as CurveExt>::ScalarExt::random(OsRng);
     //let s1 = G::random(OsRng);
-    let s2 = <halo2curves::bn256::G1 as CurveExt>::ScalarExt::random(OsRng);
+    let s2 = <halo2_pse::halo2curves::bn256::G1 as CurveExt>::ScalarExt::random(OsRng);
 
     let t0 = G::identity() * s1;
     assert!(bool::from(t0.is_identity()));
 
     let a = G::random(OsRng);
     //let t0 = a * G::ScalarExt::ONE;
-    let t0 = a * <halo2curves::bn256::G1 as CurveExt>::ScalarExt::ONE;
+    let t0 = a * <halo2_pse::halo2curves::bn256::G1 as CurveExt>::ScalarExt::ONE;
     assert_eq!(a, t0);
 
-    let t0 = a * <halo2curves::bn256::G1 as CurveExt>::ScalarExt::ZERO;
+    let t0 = a * <halo2_pse::halo2curves::bn256::G1 as CurveExt>::ScalarExt::ZERO;
     assert!(bool::from(t0.is_identity()));
 
     let t0 = a * s1 + a * s2;
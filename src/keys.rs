@@ -0,0 +1,357 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use halo2_pse::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_pse::halo2curves::ff::PrimeField;
+use halo2_pse::plonk::{self, keygen_pk, keygen_vk, Circuit, ConstraintSystem, ProvingKey, VerifyingKey};
+use halo2_pse::poly::commitment::Params;
+use halo2_pse::poly::kzg::commitment::ParamsKZG;
+use halo2_pse::SerdeFormat;
+use rand::rngs::OsRng;
+
+// Key management for circuits proven with KZG on BN254 (PSE halo2, where proving and
+// verifying keys can be serialized). A cache directory holds :
+//   params_k{k}.bin          : KZG parameters of a ceremony for circuits of 2^k rows
+//   params_k{k}_insecure.bin : parameters of a local setup, for tests and development
+//   {name}.vk, {name}.pk     : keys of the circuit 'name'
+// Every key file starts with a header binding it to the circuit it was generated for :
+//   magic (4 bytes) || k (u32 LE) || circuit digest (32 bytes) || vk digest (32 bytes) || key bytes
+// The circuit digest hashes the pinned constraint system, which configure alone builds : loading
+// compares it without any keygen, and checks the key read against the vk digest. A change made
+// only in synthesize (constants, selector rows, copy constraints) keeps the circuit digest, it
+// shows in the vk digest of a regenerated verifying key, which KeyCache::verify compares.
+
+const MAGIC: &[u8; 4] = b"FSK2";
+const FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+pub type Digest = [u8; 32];
+
+#[derive(Debug)]
+pub enum KeyError {
+    Io(io::Error),
+    Keygen(plonk::Error),
+    // the file is not a key file (wrong magic) or was written for another k
+    BadHeader { path: PathBuf },
+    // the circuit changed since the keys were generated : delete the file to regenerate
+    CircuitChanged { path: PathBuf, stored: Digest, current: Digest },
+    // the key read is not the one the header was written for
+    Corrupted { path: PathBuf },
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Io(e) => write!(f, "key cache i/o error: {}", e),
+            KeyError::Keygen(e) => write!(f, "key generation failed: {:?}", e),
+            KeyError::BadHeader { path } => write!(f, "{} is not a key file for this k", path.display()),
+            KeyError::CircuitChanged { path, stored, current } => write!(
+                f,
+                "{} was generated for circuit {} but the current circuit is {}, delete it to regenerate the keys",
+                path.display(),
+                hex(stored),
+                hex(current)
+            ),
+            KeyError::Corrupted { path } => write!(f, "{} doesn't hold the key its header was written for", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<io::Error> for KeyError {
+    fn from(e: io::Error) -> Self {
+        KeyError::Io(e)
+    }
+}
+
+impl From<plonk::Error> for KeyError {
+    fn from(e: plonk::Error) -> Self {
+        KeyError::Keygen(e)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn digest(personal: &[u8; 16], k: u32, data: &[u8]) -> Digest {
+    let mut hasher = blake2b_simd::Params::new().hash_length(32).personal(personal).to_state();
+    hasher.update(&k.to_le_bytes());
+    hasher.update(data);
+    hasher.finalize().as_bytes().try_into().unwrap()
+}
+
+// blake2b(k || pinned constraint system of C), only runs configure
+pub fn circuit_digest<C: Circuit<Fr>>(k: u32) -> Digest {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    C::configure(&mut cs);
+    digest(b"FinSeq_CircuitId", k, format!("{:?}", cs.pinned()).as_bytes())
+}
+
+// blake2b(k || transcript representation of the verifying key)
+fn vk_digest(k: u32, vk: &VerifyingKey<G1Affine>) -> Digest {
+    digest(b"FinSeq_VkDigest_", k, vk.transcript_repr().to_repr().as_ref())
+}
+
+
+// == Cache == //
+
+pub struct KeyCache {
+    dir: PathBuf,
+}
+
+impl KeyCache {
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self { dir: dir.as_ref().to_path_buf() })
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    // Parameters of a ceremony, dropped in the cache directory : an error if they are missing
+    pub fn params(&self, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+        let path = self.path(&format!("params_k{}.bin", k));
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is missing, KZG parameters come from a ceremony", path.display()),
+            ));
+        }
+        read_params(&path, k)
+    }
+
+    // [WARNING] a local setup knows its toxic waste, anyone holding it forges proofs : for tests
+    // and development only. Stored apart from the ceremony parameters, params() never reads it.
+    pub fn setup_insecure(&self, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+        let path = self.path(&format!("params_k{}_insecure.bin", k));
+        if path.exists() {
+            return read_params(&path, k);
+        }
+
+        let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        params.write(&mut writer)?;
+        writer.flush()?;
+        Ok(params)
+    }
+
+    // Checked against the configure of C, without keygen (see verify for synthesize)
+    pub fn load_vk<C: Circuit<Fr>>(&self, name: &str, params: &ParamsKZG<Bn256>) -> Result<VerifyingKey<G1Affine>, KeyError> {
+        let path = self.path(&format!("{}.vk", name));
+        let (mut reader, stored) = open_checked::<C>(&path, params.k())?;
+        let vk = VerifyingKey::read::<_, C>(&mut reader, FORMAT)?;
+        check_vk(&path, params.k(), &vk, &stored)?;
+        Ok(vk)
+    }
+
+    pub fn load_pk<C: Circuit<Fr>>(&self, name: &str, params: &ParamsKZG<Bn256>) -> Result<ProvingKey<G1Affine>, KeyError> {
+        let path = self.path(&format!("{}.pk", name));
+        let (mut reader, stored) = open_checked::<C>(&path, params.k())?;
+        let pk = ProvingKey::read::<_, C>(&mut reader, FORMAT)?;
+        check_vk(&path, params.k(), pk.get_vk(), &stored)?;
+        Ok(pk)
+    }
+
+    // The stored keys are the ones 'circuit' (without witnesses) generates now, synthesize
+    // included. Costs a keygen_vk, to run once after changing a circuit rather than on each load.
+    pub fn verify<C: Circuit<Fr>>(&self, name: &str, params: &ParamsKZG<Bn256>, circuit: &C) -> Result<(), KeyError> {
+        let path = self.path(&format!("{}.vk", name));
+        let (_, stored) = open_checked::<C>(&path, params.k())?;
+        let current = vk_digest(params.k(), &keygen_vk(params, circuit)?);
+        if stored != current {
+            return Err(KeyError::CircuitChanged { path, stored, current });
+        }
+        Ok(())
+    }
+
+    // runs keygen for 'circuit' (without witnesses) and stores both keys
+    pub fn generate<C: Circuit<Fr>>(
+        &self,
+        name: &str,
+        params: &ParamsKZG<Bn256>,
+        circuit: &C,
+    ) -> Result<ProvingKey<G1Affine>, KeyError> {
+        let vk = keygen_vk(params, circuit)?;
+        let k = params.k();
+        let digests = (circuit_digest::<C>(k), vk_digest(k, &vk));
+        let pk = keygen_pk(params, vk, circuit)?;
+
+        let mut writer = create_with_header(&self.path(&format!("{}.vk", name)), k, &digests)?;
+        pk.get_vk().write(&mut writer, FORMAT)?;
+        writer.flush()?;
+        let mut writer = create_with_header(&self.path(&format!("{}.pk", name)), k, &digests)?;
+        pk.write(&mut writer, FORMAT)?;
+        writer.flush()?;
+        Ok(pk)
+    }
+
+    // Keys from the cache, generated only on the very first start-up (no key file yet).
+    // Stale keys are an error, they are never silently replaced.
+    pub fn load_or_generate<C: Circuit<Fr>>(
+        &self,
+        name: &str,
+        params: &ParamsKZG<Bn256>,
+        circuit: &C,
+    ) -> Result<ProvingKey<G1Affine>, KeyError> {
+        if self.path(&format!("{}.pk", name)).exists() {
+            self.load_pk::<C>(name, params)
+        } else {
+            self.generate(name, params, circuit)
+        }
+    }
+}
+
+fn read_params(path: &Path, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+    let params = ParamsKZG::<Bn256>::read(&mut BufReader::new(File::open(path)?))?;
+    if params.k() != k {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} holds k = {}", path.display(), params.k())));
+    }
+    Ok(params)
+}
+
+// (circuit digest, vk digest)
+fn create_with_header(path: &Path, k: u32, (circuit, vk): &(Digest, Digest)) -> io::Result<BufWriter<File>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&k.to_le_bytes())?;
+    writer.write_all(circuit)?;
+    writer.write_all(vk)?;
+    Ok(writer)
+}
+
+// opens a key file and checks its header against the configure of C, returns the vk digest
+fn open_checked<C: Circuit<Fr>>(path: &Path, k: u32) -> Result<(BufReader<File>, Digest), KeyError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 4];
+    let mut k_bytes = [0u8; 4];
+    let mut stored = [0u8; 32];
+    let mut vk = [0u8; 32];
+    reader.read_exact(&mut magic)?;
+    reader.read_exact(&mut k_bytes)?;
+    reader.read_exact(&mut stored)?;
+    reader.read_exact(&mut vk)?;
+    if &magic != MAGIC || u32::from_le_bytes(k_bytes) != k {
+        return Err(KeyError::BadHeader { path: path.to_path_buf() });
+    }
+
+    let current = circuit_digest::<C>(k);
+    if stored != current {
+        return Err(KeyError::CircuitChanged { path: path.to_path_buf(), stored, current });
+    }
+    Ok((reader, vk))
+}
+
+fn check_vk(path: &Path, k: u32, vk: &VerifyingKey<G1Affine>, stored: &Digest) -> Result<(), KeyError> {
+    if vk_digest(k, vk) != *stored {
+        return Err(KeyError::Corrupted { path: path.to_path_buf() });
+    }
+    Ok(())
+}
+
+
+// == Test circuits : a * b == c, a version with an extra gate and one laid out at another row == //
+
+#[cfg(test)]
+mod test_circuits {
+    use halo2_pse::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_pse::halo2curves::bn256::Fr;
+    use halo2_pse::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Selector};
+    use halo2_pse::poly::Rotation;
+
+    // OFFSET only moves the region in synthesize, configure is the same
+    #[derive(Clone, Default)]
+    pub struct Mul<const EXTRA: bool, const OFFSET: usize = 0> {
+        pub a: Value<Fr>,
+        pub b: Value<Fr>,
+    }
+
+    impl<const EXTRA: bool, const OFFSET: usize> Circuit<Fr> for Mul<EXTRA, OFFSET> {
+        type Config = ([Column<Advice>; 3], Selector);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let advices = [(); 3].map(|_| meta.advice_column());
+            let q = meta.selector();
+            meta.create_gate("a * b == c", |meta| {
+                let [a, b, c] = advices.map(|col| meta.query_advice(col, Rotation::cur()));
+                let q = meta.query_selector(q);
+                if EXTRA {
+                    vec![q.clone() * (a.clone() * b.clone() - c.clone()), q * (a - b)]
+                } else {
+                    vec![q * (a * b - c)]
+                }
+            });
+            (advices, q)
+        }
+
+        fn synthesize(&self, (advices, q): Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+            layouter.assign_region(
+                || "mul",
+                |mut region| {
+                    q.enable(&mut region, OFFSET)?;
+                    region.assign_advice(|| "a", advices[0], OFFSET, || self.a)?;
+                    region.assign_advice(|| "b", advices[1], OFFSET, || self.b)?;
+                    region.assign_advice(|| "c", advices[2], OFFSET, || self.a * self.b)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+}
+
+#[test]
+pub fn test_key_cache() {
+    use test_circuits::Mul;
+
+    let dir = std::env::temp_dir().join(format!("finality_sequencer_keys_{}", std::process::id()));
+    let cache = KeyCache::new(&dir).unwrap();
+    let k = 4;
+
+    // no ceremony parameters : an error, the insecure setup is asked for explicitly
+    assert_eq!(cache.params(k).err().unwrap().kind(), io::ErrorKind::NotFound);
+    let params = cache.setup_insecure(k).unwrap();
+    assert_eq!(cache.setup_insecure(k).unwrap().k(), k);
+    assert!(cache.params(k).is_err());
+
+    // first start-up : keygen, then every later start-up loads the same keys
+    let pk = cache.load_or_generate("mul", &params, &Mul::<false>::default()).unwrap();
+    let vk = cache.load_vk::<Mul<false>>("mul", &params).unwrap();
+    assert_eq!(vk.transcript_repr(), pk.get_vk().transcript_repr());
+    let reloaded = cache.load_or_generate("mul", &params, &Mul::<false>::default()).unwrap();
+    assert_eq!(reloaded.get_vk().transcript_repr(), pk.get_vk().transcript_repr());
+    cache.verify("mul", &params, &Mul::<false>::default()).unwrap();
+
+    // configure changed : loading fails instead of returning stale keys
+    let digest = circuit_digest::<Mul<false>>(k);
+    assert_ne!(digest, circuit_digest::<Mul<true>>(k));
+    let err = cache.load_or_generate("mul", &params, &Mul::<true>::default()).err().unwrap();
+    assert!(matches!(err, KeyError::CircuitChanged { .. }));
+    assert!(matches!(cache.load_vk::<Mul<true>>("mul", &params), Err(KeyError::CircuitChanged { .. })));
+
+    // same configure, only synthesize changed (the selector and the cells moved one row down) :
+    // the keys load, verify regenerates the vk and finds the change
+    assert_eq!(digest, circuit_digest::<Mul<false, 1>>(k));
+    let err = cache.verify("mul", &params, &Mul::<false, 1>::default()).err().unwrap();
+    assert!(matches!(err, KeyError::CircuitChanged { .. }));
+
+    // a key file whose key isn't the one its header was written for
+    let path = dir.join("mul.vk");
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[40] ^= 1;
+    fs::write(&path, bytes).unwrap();
+    assert!(matches!(cache.load_vk::<Mul<false>>("mul", &params), Err(KeyError::Corrupted { .. })));
+
+    let other_params = cache.setup_insecure(k + 1).unwrap();
+    assert!(matches!(cache.load_pk::<Mul<false>>("mul", &other_params), Err(KeyError::BadHeader { .. })));
+
+    fs::remove_dir_all(dir).unwrap();
+}
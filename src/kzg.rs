@@ -0,0 +1,301 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use halo2_pse::halo2curves::bn256::{Bn256, Fq as Base, Fr as Scalar, G1Affine, G2Affine, G1, G2};
+use halo2_pse::halo2curves::ff::{Field, PrimeField};
+use halo2_pse::halo2curves::group::{Curve, Group, GroupEncoding};
+use halo2_pse::halo2curves::pairing::Engine;
+use rand::{CryptoRng, RngCore};
+
+use halo2_pse::arithmetic::{best_fft, best_multiexp};
+
+// KZG commitment to an ordered batch : the message hash sequenced at index i is the
+// evaluation p(ω^i) of a polynomial of degree < 2^k, and the batch commitment is C = [p(s)]_1,
+// posted with k (the domain the indices are taken in).
+// Opening index i is a single G1 element π = [q(s)]_1 with q(X) = (p(X) - p(ω^i)) / (X - ω^i),
+// checked with e(C - [y]_1, [1]_2) == e(π, [s]_2 - [ω^i]_2).
+// Message hashes live in Fq, which is larger than Fr : a hash >= r has no evaluation (reducing it
+// would let m and m - r open the same slot), so such a batch can't be committed.
+
+// == Structured reference string == //
+
+// [s^j]_1 for j < 2^k, [1]_2 and [s]_2
+#[derive(Debug, Clone)]
+pub struct Srs {
+    pub k: u32,
+    pub g1: Vec<G1Affine>,
+    pub g2: G2Affine,
+    pub s_g2: G2Affine,
+}
+
+impl Srs {
+    // [WARNING] the toxic waste 's' is known to the caller, only use it for tests
+    pub fn setup(k: u32, rng: &mut (impl CryptoRng + RngCore)) -> Self {
+        let s = Scalar::random(rng);
+        let g = G1::generator();
+
+        let mut powers = vec![g; 1 << k];
+        for j in 1..powers.len() {
+            powers[j] = powers[j - 1] * s;
+        }
+        let mut g1 = vec![G1Affine::default(); 1 << k];
+        G1::batch_normalize(&powers, &mut g1);
+
+        let g2 = G2::generator();
+        Self { k, g1, g2: g2.to_affine(), s_g2: (g2 * s).to_affine() }
+    }
+
+    // File layout : k (u32 LE) || 2^k compressed G1 points || compressed [1]_2 || compressed [s]_2
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&self.k.to_le_bytes())?;
+        for point in self.g1.iter() {
+            writer.write_all(point.to_bytes().as_ref())?;
+        }
+        writer.write_all(self.g2.to_bytes().as_ref())?;
+        writer.write_all(self.s_g2.to_bytes().as_ref())?;
+        writer.flush()
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut k_bytes = [0u8; 4];
+        reader.read_exact(&mut k_bytes)?;
+        let k = u32::from_le_bytes(k_bytes);
+        if k > Scalar::S {
+            return Err(invalid_data("srs degree exceeds the 2-adicity of the scalar field"));
+        }
+        // the file holds the 2^k points before they are allocated
+        let g1_len = <G1Affine as GroupEncoding>::Repr::default().as_ref().len() as u64;
+        let g2_len = <G2Affine as GroupEncoding>::Repr::default().as_ref().len() as u64;
+        if len != 4 + (g1_len << k) + 2 * g2_len {
+            return Err(invalid_data("srs file length doesn't match its degree"));
+        }
+
+        let g1 = (0..1usize << k)
+            .map(|_| read_point::<G1Affine>(&mut reader))
+            .collect::<io::Result<Vec<G1Affine>>>()?;
+        let g2 = read_point::<G2Affine>(&mut reader)?;
+        let s_g2 = read_point::<G2Affine>(&mut reader)?;
+
+        Ok(Self { k, g1, g2, s_g2 })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_point<C: GroupEncoding>(reader: &mut impl Read) -> io::Result<C> {
+    let mut repr = C::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    Option::from(C::from_bytes(&repr)).ok_or_else(|| invalid_data("invalid curve point"))
+}
+
+// generator of the multiplicative subgroup of order 2^k
+pub fn root_of_unity(k: u32) -> Scalar {
+    assert!(k <= Scalar::S);
+    Scalar::ROOT_OF_UNITY.pow_vartime([1u64 << (Scalar::S - k)])
+}
+
+// the evaluation of a message hash, None if it doesn't fit in the scalar field
+pub fn message_eval(message: Base) -> Option<Scalar> {
+    Scalar::from_repr(message.to_repr()).into()
+}
+
+
+// == Batch commitment == //
+
+pub struct KzgBatch {
+    k: u32,
+    evals: Vec<Scalar>,     // p(ω^i), zero padded up to 2^k
+    coeffs: Vec<Scalar>,
+    commitment: G1Affine,
+}
+
+// What the sequencer posts for a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KzgCommitment {
+    pub k: u32,
+    pub point: G1Affine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KzgOpening {
+    pub k: u32,
+    pub index: u64,
+    pub value: Scalar,
+    pub proof: G1Affine,
+}
+
+impl KzgBatch {
+    // commit to the ordered message hashes, using the smallest domain that fits the batch.
+    // None if the batch is larger than the srs or a message hash is >= r
+    pub fn commit(srs: &Srs, messages: &[Base]) -> Option<Self> {
+        let k = messages.len().max(1).next_power_of_two().trailing_zeros();
+        if k > srs.k {
+            return None;
+        }
+
+        let mut evals = vec![Scalar::zero(); 1 << k];
+        for (eval, message) in evals.iter_mut().zip(messages.iter()) {
+            *eval = message_eval(*message)?;
+        }
+
+        // interpolate : coeffs = IFFT(evals)
+        let mut coeffs = evals.clone();
+        best_fft(&mut coeffs, root_of_unity(k).invert().unwrap(), k);
+        let n_inv = Scalar::from(1u64 << k).invert().unwrap();
+        coeffs.iter_mut().for_each(|c| *c *= n_inv);
+
+        let commitment = best_multiexp(&coeffs, &srs.g1[..coeffs.len()]).to_affine();
+
+        Some(Self { k, evals, coeffs, commitment })
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    pub fn commitment(&self) -> KzgCommitment {
+        KzgCommitment { k: self.k, point: self.commitment }
+    }
+
+    pub fn open(&self, srs: &Srs, index: u64) -> Option<KzgOpening> {
+        let value = *self.evals.get(index as usize)?;
+        let z = root_of_unity(self.k).pow_vartime([index]);
+
+        // synthetic division of p(X) - y by (X - z), the remainder is zero
+        let mut quotient = vec![Scalar::zero(); self.coeffs.len() - 1];
+        let mut carry = Scalar::zero();
+        for j in (1..self.coeffs.len()).rev() {
+            carry = self.coeffs[j] + carry * z;
+            quotient[j - 1] = carry;
+        }
+
+        let proof = if quotient.is_empty() {
+            G1Affine::default()
+        } else {
+            best_multiexp(&quotient, &srs.g1[..quotient.len()]).to_affine()
+        };
+        Some(KzgOpening { k: self.k, index, value, proof })
+    }
+}
+
+impl KzgOpening {
+    // 'message' was sequenced at 'index' in the batch of 'commitment', over the domain it was
+    // posted with
+    pub fn verify(&self, srs: &Srs, commitment: &KzgCommitment, message: Base) -> bool {
+        let k = commitment.k;
+        if self.k != k || k > Scalar::S || k > srs.k || self.index >= 1 << k || Some(self.value) != message_eval(message) {
+            return false;
+        }
+        let z = root_of_unity(k).pow_vartime([self.index]);
+
+        let lhs = (G1::from(commitment.point) - srs.g1[0] * self.value).to_affine();
+        let rhs = (G2::from(srs.s_g2) - srs.g2 * z).to_affine();
+        Bn256::pairing(&lhs, &srs.g2) == Bn256::pairing(&self.proof, &rhs)
+    }
+}
+
+
+#[test]
+pub fn test_kzg_batch_opening() {
+    use rand::rngs::OsRng;
+
+    let srs = Srs::setup(5, &mut OsRng);
+    let messages = (0..19).map(|_| Base::random(OsRng)).collect::<Vec<Base>>();
+    let batch = KzgBatch::commit(&srs, &messages).unwrap();
+    assert_eq!(batch.k(), 5);
+
+    for (i, message) in messages.iter().enumerate() {
+        let opening = batch.open(&srs, i as u64).unwrap();
+        assert!(opening.verify(&srs, &batch.commitment(), *message));
+        assert!(!opening.verify(&srs, &batch.commitment(), *message + Base::one()));
+    }
+
+    // the opening of index 3 does not prove anything about index 4
+    let mut opening = batch.open(&srs, 3).unwrap();
+    opening.index = 4;
+    assert!(!opening.verify(&srs, &batch.commitment(), messages[3]));
+
+    // padded slots open to zero
+    let opening = batch.open(&srs, 25).unwrap();
+    assert!(opening.verify(&srs, &batch.commitment(), Base::zero()));
+    assert!(batch.open(&srs, 32).is_none());
+
+    // a message hash >= r has no evaluation : it can't be committed, nor verified
+    let too_large = -Base::one();
+    assert!(KzgBatch::commit(&srs, &[too_large]).is_none());
+    // nor can a batch larger than the srs
+    assert!(KzgBatch::commit(&srs, &[Base::one(); 33]).is_none());
+    assert!(!opening.verify(&srs, &batch.commitment(), too_large));
+
+    // the domain is the one posted with the commitment, an opening over another one is rejected
+    let commitment = batch.commitment();
+    assert!(!opening.verify(&srs, &KzgCommitment { k: 40, ..commitment }, Base::zero()));
+    assert!(!opening.verify(&srs, &KzgCommitment { k: 4, ..commitment }, Base::zero()));
+    assert!(!KzgOpening { k: 4, ..batch.open(&srs, 3).unwrap() }.verify(&srs, &commitment, messages[3]));
+    let small = KzgBatch::commit(&srs, &messages[..4]).unwrap();
+    assert!(!small.open(&srs, 3).unwrap().verify(&srs, &KzgCommitment { k: 5, ..small.commitment() }, messages[3]));
+}
+
+#[test]
+pub fn test_srs_file() {
+    use rand::rngs::OsRng;
+
+    let srs = Srs::setup(3, &mut OsRng);
+    let path = std::env::temp_dir().join(format!("finality_sequencer_srs_{}.bin", std::process::id()));
+    srs.write(&path).unwrap();
+    let loaded = Srs::read(&path).unwrap();
+
+    assert_eq!(loaded.k, srs.k);
+    assert_eq!(loaded.g1, srs.g1);
+    assert_eq!(loaded.g2, srs.g2);
+    assert_eq!(loaded.s_g2, srs.s_g2);
+
+    // a file claiming more points than it holds is rejected before they are allocated
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[..4].copy_from_slice(&20u32.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    assert_eq!(Srs::read(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+}
+
+// cargo test --release bench_kzg_vs_merkle -- --ignored --nocapture
+#[test]
+#[ignore]
+pub fn bench_kzg_vs_merkle() {
+    use std::time::Instant;
+    use rand::rngs::OsRng;
+    use crate::merkle::MerkleTree;
+
+    const K: u32 = 12;
+    let messages = (0..1 << K).map(|_| Base::random(OsRng)).collect::<Vec<Base>>();
+    let srs = Srs::setup(K, &mut OsRng);
+
+    let now = Instant::now();
+    let batch = KzgBatch::commit(&srs, &messages).unwrap();
+    println!("KZG commit ({} txs) took: {:?}", messages.len(), now.elapsed());
+    let now = Instant::now();
+    let opening = batch.open(&srs, 7).unwrap();
+    println!("KZG open took: {:?}", now.elapsed());
+    let now = Instant::now();
+    assert!(opening.verify(&srs, &batch.commitment(), messages[7]));
+    println!("KZG verify took: {:?}, proof size: {} bytes", now.elapsed(), opening.proof.to_bytes().as_ref().len());
+
+    let now = Instant::now();
+    let mut tree = MerkleTree::<Base, { K as usize }>::new();
+    messages.iter().for_each(|m| { tree.append(*m); });
+    println!("Merkle commit ({} txs) took: {:?}", messages.len(), now.elapsed());
+    let now = Instant::now();
+    let proof = tree.prove(7).unwrap();
+    println!("Merkle open took: {:?}", now.elapsed());
+    let now = Instant::now();
+    assert!(proof.verify(tree.root(), messages[7]));
+    println!("Merkle verify took: {:?}, proof size: {} bytes", now.elapsed(), proof.siblings.len() * 32);
+}
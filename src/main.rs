@@ -1,9 +1,56 @@
 mod halo2;
 mod schnorr;
+mod merkle;
+mod smt;
+mod mmr;
+mod kzg;
+#[cfg(feature = "ipa-pasta")]
+mod circuits;
+#[cfg(any(feature = "ipa-pasta", feature = "kzg-bn254"))]
+mod backend;
+#[cfg(feature = "kzg-bn254")]
+mod keys;
+#[cfg(any(feature = "ipa-pasta", feature = "kzg-bn254"))]
+mod report;
+#[cfg(feature = "bls")]
+mod bls;
 
 mod cairo;
 
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        // cargo run --release -- report : cost of every circuit as JSON
+        #[cfg(any(feature = "ipa-pasta", feature = "kzg-bn254"))]
+        Some("report") => {
+            println!("{}", report::report_json());
+            return;
+        }
+        // cargo run --release -- fees [prices.json] : resources and fee of a dispute verification
+        Some("fees") => {
+            let prices = match args.get(2) {
+                Some(path) => exit_on_error(cairo::fees::PriceTable::from_file(std::path::Path::new(path))),
+                None => cairo::fees::PriceTable::default(),
+            };
+            println!("{}", exit_on_error(cairo::fees::dispute_fee_json(&prices)));
+            return;
+        }
+        // cargo run --release -- debug [--forged] [--trace-file trace.txt] : dispute verification
+        // traced step by step, with the failing assertion and its source when the claim is rejected
+        Some("debug") => {
+            let forged = args.iter().any(|arg| arg == "--forged");
+            let trace_file = args.iter().position(|arg| arg == "--trace-file").and_then(|i| args.get(i + 1));
+            println!("{}", exit_on_error(cairo::trace::debug_dispute(forged, trace_file.map(std::path::Path::new))));
+            return;
+        }
+        // cargo run --release -- hashes : hash of every Cairo artifact, to pin in the registry
+        Some("hashes") => {
+            println!("{}", cairo::registry::hashes());
+            return;
+        }
+        _ => {}
+    }
+
     println!("Finality sequencer!");
 
     //=== User side ===//
@@ -43,11 +90,21 @@ fn main() {
     // 6. Submit a signature from the sequencer and tx.
     
     // === Smart Contract === //
-    // 7. verify the signature
+    // 7. verify the signature (cairo_program/dispute.cairo, run locally by cairo::dispute)
+        // the contract verifies the MuSig above on the STARK curve with Pedersen hashes, a
+        // separate co-signature from the BN254/Poseidon one of schnorr.rs : those can't be disputed
     // 8. check out the commitment to do with the index USING storage proof!!
 
 }
 
+// the value of a command, or its error on stderr and a non-zero exit code
+fn exit_on_error<T>(result: Result<T, cairo::CairoError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1)
+    })
+}
+
 #[test]
 fn halo2_test() {
     let vsc = halo2::rand_vec_scalar(3);
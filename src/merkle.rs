@@ -0,0 +1,395 @@
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+
+use crate::halo2::poseidon_hash;
+
+// Append-only Poseidon Merkle tree over the ordered batch of message hashes.
+// Leaf i is the message hash the sequencer committed to index i, so the root
+// commits to the whole ordered batch. Empty slots hold the zero leaf.
+
+// == Hashing == //
+
+// H(left || right)
+pub fn hash_nodes<F: FromUniformBytes<64> + Ord>(left: F, right: F) -> F {
+    poseidon_hash(vec![left, right])
+}
+
+// zero_hashes[h] is the root of an empty subtree of height h (zero_hashes[0] is the empty leaf)
+pub fn zero_hashes<F: FromUniformBytes<64> + Ord>(depth: usize) -> Vec<F> {
+    let mut zeros = vec![F::ZERO; depth + 1];
+    for h in 0..depth {
+        zeros[h + 1] = hash_nodes(zeros[h], zeros[h]);
+    }
+    zeros
+}
+
+
+// == Incremental frontier == //
+
+// Keeps only the O(DEPTH) left-most "filled subtrees" needed to compute the root
+// after each append, so the sequencer can track the batch root without storing leaves.
+#[derive(Debug, Clone)]
+pub struct MerkleFrontier<F, const DEPTH: usize> {
+    size: u64,
+    frontier: Vec<F>,   // frontier[h] : last complete left node at height h
+    zeros: Vec<F>,
+    full_root: Option<F>,   // set by the append that fills the tree, no empty slot is left to walk
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> MerkleFrontier<F, DEPTH> {
+    pub fn new() -> Self {
+        Self { size: 0, frontier: vec![F::ZERO; DEPTH], zeros: zero_hashes(DEPTH), full_root: None }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // append a leaf at index 'size' and return that index
+    pub fn append(&mut self, leaf: F) -> u64 {
+        assert!(self.size < capacity(DEPTH), "merkle tree is full");
+
+        let index = self.size;
+        let mut node = leaf;
+        let mut idx = index;
+        for h in 0..DEPTH {
+            if idx & 1 == 0 {
+                // the node is a left child : remember it and stop
+                self.frontier[h] = node;
+                self.size += 1;
+                return index;
+            }
+            node = hash_nodes(self.frontier[h], node);
+            idx >>= 1;
+        }
+        // only right children up to the top : this leaf fills the tree and node is its root
+        self.full_root = Some(node);
+        self.size += 1;
+        index
+    }
+
+    pub fn root(&self) -> F {
+        if let Some(root) = self.full_root {
+            return root;
+        }
+        let mut node = self.zeros[0];
+        let mut idx = self.size;
+        // walk the path of the first empty slot, left siblings come from the frontier
+        for h in 0..DEPTH {
+            node = if idx & 1 == 1 {
+                hash_nodes(self.frontier[h], node)
+            } else {
+                hash_nodes(node, self.zeros[h])
+            };
+            idx >>= 1;
+        }
+        node
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> Default for MerkleFrontier<F, DEPTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+// == Full tree (proof generation) == //
+
+#[derive(Debug, Clone)]
+pub struct MerkleTree<F, const DEPTH: usize> {
+    layers: Vec<Vec<F>>,    // layers[0] : leaves, layers[h] : non-empty nodes at height h
+    zeros: Vec<F>,
+}
+
+// Authentication path for the leaf at 'index'. siblings[h] is the sibling at height h.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof<F, const DEPTH: usize> {
+    pub index: u64,
+    pub siblings: Vec<F>,
+}
+
+// Proves that the first 'old_size' leaves of the tree with 'new_size' leaves are exactly
+// the leaves of the older tree. Both roots are recomputed along the path of the first
+// empty slot of the old tree:
+//  - left[h]  : complete left nodes (shared by both trees) where bit h of old_size is 1
+//  - right[h] : right siblings in the new tree where bit h of old_size is 0
+//  - boundary : the leaf at index old_size in the new tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof<F, const DEPTH: usize> {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub boundary: F,
+    pub left: Vec<F>,
+    pub right: Vec<F>,
+}
+
+pub fn capacity(depth: usize) -> u64 {
+    assert!(depth < 64, "depth must be smaller than 64");
+    1u64 << depth
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> MerkleTree<F, DEPTH> {
+    pub fn new() -> Self {
+        Self { layers: vec![vec![]; DEPTH + 1], zeros: zero_hashes(DEPTH) }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.layers[0].len() as u64
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<F> {
+        self.layers[0].get(index as usize).copied()
+    }
+
+    // append a leaf and update the path to the root, return its index
+    pub fn append(&mut self, leaf: F) -> u64 {
+        assert!(self.size() < capacity(DEPTH), "merkle tree is full");
+
+        let index = self.size();
+        self.layers[0].push(leaf);
+
+        let mut idx = index as usize;
+        for h in 0..DEPTH {
+            let parent = hash_nodes(self.node(h, idx & !1), self.node(h, idx | 1));
+            idx >>= 1;
+            if idx < self.layers[h + 1].len() {
+                self.layers[h + 1][idx] = parent;
+            } else {
+                self.layers[h + 1].push(parent);
+            }
+        }
+        index
+    }
+
+    pub fn root(&self) -> F {
+        self.node(DEPTH, 0)
+    }
+
+    // node at height h, position idx of the current tree
+    fn node(&self, h: usize, idx: usize) -> F {
+        self.layers[h].get(idx).copied().unwrap_or(self.zeros[h])
+    }
+
+    // node at height h, position idx of the tree as it was with 'size' leaves
+    fn historical_node(&self, h: usize, idx: u64, size: u64) -> F {
+        let start = idx << h;
+        if start >= size {
+            return self.zeros[h];
+        }
+        if (idx + 1) << h <= size || size == self.size() {
+            // the subtree was already complete (or this is the current tree)
+            return self.node(h, idx as usize);
+        }
+        hash_nodes(
+            self.historical_node(h - 1, idx << 1, size),
+            self.historical_node(h - 1, (idx << 1) | 1, size),
+        )
+    }
+
+    // root of the tree when it had 'size' leaves
+    pub fn root_at(&self, size: u64) -> F {
+        assert!(size <= self.size(), "size exceeds the current tree");
+        self.historical_node(DEPTH, 0, size)
+    }
+
+    pub fn prove(&self, index: u64) -> Option<InclusionProof<F, DEPTH>> {
+        if index >= self.size() {
+            return None;
+        }
+        let siblings = (0..DEPTH)
+            .map(|h| self.node(h, ((index >> h) ^ 1) as usize))
+            .collect();
+        Some(InclusionProof { index, siblings })
+    }
+
+    // consistency proof between the tree with 'old_size' leaves and the current tree
+    pub fn prove_consistency(&self, old_size: u64) -> Option<ConsistencyProof<F, DEPTH>> {
+        let new_size = self.size();
+        if old_size > new_size {
+            return None;
+        }
+
+        let mut left = vec![];
+        let mut right = vec![];
+        if old_size < capacity(DEPTH) {
+            for h in 0..DEPTH {
+                let idx = old_size >> h;
+                if idx & 1 == 1 {
+                    left.push(self.node(h, (idx - 1) as usize));
+                } else {
+                    right.push(self.node(h, (idx + 1) as usize));
+                }
+            }
+        }
+        let boundary = self.leaf(old_size).unwrap_or(self.zeros[0]);
+
+        Some(ConsistencyProof { old_size, new_size, boundary, left, right })
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> Default for MerkleTree<F, DEPTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> InclusionProof<F, DEPTH> {
+    // recompute the root from 'leaf' and the authentication path
+    pub fn compute_root(&self, leaf: F) -> F {
+        let mut node = leaf;
+        for (h, sibling) in self.siblings.iter().enumerate() {
+            node = if (self.index >> h) & 1 == 0 {
+                hash_nodes(node, *sibling)
+            } else {
+                hash_nodes(*sibling, node)
+            };
+        }
+        node
+    }
+
+    pub fn verify(&self, root: F, leaf: F) -> bool {
+        self.index < capacity(DEPTH)
+            && self.siblings.len() == DEPTH
+            && self.compute_root(leaf) == root
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord, const DEPTH: usize> ConsistencyProof<F, DEPTH> {
+    pub fn verify(&self, old_root: F, new_root: F) -> bool {
+        if self.old_size > self.new_size || self.new_size > capacity(DEPTH) {
+            return false;
+        }
+        if self.old_size == capacity(DEPTH) {
+            // a full tree can not grow anymore
+            return old_root == new_root && self.left.is_empty() && self.right.is_empty();
+        }
+        let n_left = self.old_size.count_ones() as usize;
+        if self.left.len() != n_left || self.right.len() != DEPTH - n_left {
+            return false;
+        }
+
+        let zeros = zero_hashes::<F>(DEPTH);
+        let mut old_node = zeros[0];
+        let mut new_node = if self.old_size < self.new_size { self.boundary } else { zeros[0] };
+        let (mut left, mut right) = (self.left.iter(), self.right.iter());
+
+        for h in 0..DEPTH {
+            if (self.old_size >> h) & 1 == 1 {
+                let sibling = *left.next().unwrap();
+                old_node = hash_nodes(sibling, old_node);
+                new_node = hash_nodes(sibling, new_node);
+            } else {
+                old_node = hash_nodes(old_node, zeros[h]);
+                new_node = hash_nodes(new_node, *right.next().unwrap());
+            }
+        }
+
+        old_node == old_root && new_node == new_root
+    }
+}
+
+
+#[test]
+pub fn test_merkle_inclusion() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = MerkleTree::<Base, 8>::new();
+    let leaves = (0..13).map(|_| Base::random(OsRng)).collect::<Vec<Base>>();
+    for leaf in leaves.iter() {
+        tree.append(*leaf);
+    }
+
+    let root = tree.root();
+    for (i, leaf) in leaves.iter().enumerate() {
+        let proof = tree.prove(i as u64).unwrap();
+        assert!(proof.verify(root, *leaf));
+        assert!(!proof.verify(root, *leaf + Base::one()));
+    }
+
+    // a valid path for another index must not verify
+    let mut proof = tree.prove(3).unwrap();
+    proof.index = 4;
+    assert!(!proof.verify(root, leaves[3]));
+    assert!(tree.prove(13).is_none());
+}
+
+#[test]
+pub fn test_merkle_frontier() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = MerkleTree::<Base, 6>::new();
+    let mut frontier = MerkleFrontier::<Base, 6>::new();
+    assert_eq!(tree.root(), frontier.root());
+
+    for i in 0..20u64 {
+        let leaf = Base::random(OsRng);
+        assert_eq!(tree.append(leaf), i);
+        assert_eq!(frontier.append(leaf), i);
+        assert_eq!(tree.root(), frontier.root());
+        assert_eq!(tree.root_at(i), {
+            let mut old = MerkleFrontier::<Base, 6>::new();
+            (0..i).for_each(|j| { old.append(tree.leaf(j).unwrap()); });
+            old.root()
+        });
+    }
+}
+
+#[test]
+pub fn test_merkle_frontier_full() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = MerkleTree::<Base, 3>::new();
+    let mut frontier = MerkleFrontier::<Base, 3>::new();
+    for _ in 0..capacity(3) {
+        let leaf = Base::random(OsRng);
+        tree.append(leaf);
+        frontier.append(leaf);
+        assert_eq!(tree.root(), frontier.root());
+    }
+    assert_eq!(frontier.size(), capacity(3));
+    assert_ne!(frontier.root(), zero_hashes::<Base>(3)[3]);
+
+    // a tree of depth 0 is full with its single leaf
+    let mut single = MerkleFrontier::<Base, 0>::new();
+    single.append(Base::one());
+    assert_eq!(single.root(), Base::one());
+}
+
+#[test]
+pub fn test_merkle_consistency() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = MerkleTree::<Base, 4>::new();
+    let mut roots = vec![tree.root()];
+    for _ in 0..16 {
+        tree.append(Base::random(OsRng));
+        roots.push(tree.root());
+    }
+
+    for old_size in 0..=16u64 {
+        let proof = tree.prove_consistency(old_size).unwrap();
+        assert!(proof.verify(roots[old_size as usize], tree.root()));
+
+        // a different history must be rejected
+        if old_size > 0 {
+            assert!(!proof.verify(roots[old_size as usize - 1] + Base::one(), tree.root()));
+        }
+    }
+
+    // rewriting an old leaf breaks consistency
+    let mut forked = MerkleTree::<Base, 4>::new();
+    for i in 0..16 {
+        let leaf = if i == 2 { Base::random(OsRng) } else { tree.leaf(i).unwrap() };
+        forked.append(leaf);
+    }
+    let proof = forked.prove_consistency(5).unwrap();
+    assert!(!proof.verify(roots[5], forked.root()));
+}
@@ -0,0 +1,280 @@
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+
+use crate::halo2::poseidon_hash;
+use crate::merkle::hash_nodes;
+
+// Merkle Mountain Range over the sequencing log.
+// Leaf i is H(i || m) where i is the same index the sequencer puts in the challenge
+// e = H(R || X || m || i), so a leaf can only be opened at the index it was sequenced to.
+// The log is a list of perfect trees ("peaks"), one per set bit of the size, and the root
+// is H(size || bag) where bag folds the peaks right to left : bag = H(peak_0, H(peak_1, ...)).
+// Nodes are never rewritten on append, so proofs against any historical root stay valid.
+
+pub fn leaf_hash<F: FromUniformBytes<64> + Ord>(index: u64, message: F) -> F {
+    poseidon_hash(vec![F::from(index), message])
+}
+
+pub fn bag_peaks<F: FromUniformBytes<64> + Ord>(peaks: &[F]) -> F {
+    match peaks.split_last() {
+        None => F::ZERO,
+        Some((last, rest)) => rest.iter().rev().fold(*last, |bag, peak| hash_nodes(*peak, bag)),
+    }
+}
+
+pub fn mmr_root<F: FromUniformBytes<64> + Ord>(size: u64, peaks: &[F]) -> F {
+    hash_nodes(F::from(size), bag_peaks(peaks))
+}
+
+// (height, first leaf) of every peak of an MMR with 'size' leaves, left to right
+fn peak_positions(size: u64) -> Vec<(usize, u64)> {
+    let mut offset = 0;
+    let mut positions = vec![];
+    for h in (0..64).rev() {
+        if (size >> h) & 1 == 1 {
+            positions.push((h, offset));
+            offset += 1 << h;
+        }
+    }
+    positions
+}
+
+// (peak number, height) of the peak covering leaf 'index' in an MMR with 'size' leaves
+fn peak_of(index: u64, size: u64) -> Option<(usize, usize)> {
+    peak_positions(size)
+        .into_iter()
+        .enumerate()
+        .find(|(_, (h, offset))| index >= *offset && index - offset < (1 << h))
+        .map(|(k, (h, _))| (k, h))
+}
+
+#[derive(Debug, Clone)]
+pub struct Mmr<F> {
+    levels: Vec<Vec<F>>,    // levels[h][j] : root of the perfect tree over leaves [j * 2^h, (j+1) * 2^h)
+}
+
+// 'index' is included in the MMR with 'size' leaves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MmrProof<F> {
+    pub index: u64,
+    pub size: u64,
+    pub siblings: Vec<F>,   // path from the leaf up to its peak
+    pub peaks: Vec<F>,
+}
+
+// The MMR with 'new_size' leaves extends the MMR with 'old_size' leaves :
+// every old peak is a node of the new MMR, reached through paths[k] from old_peaks[k].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AncestryProof<F> {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub old_peaks: Vec<F>,
+    pub paths: Vec<Vec<F>>,
+    pub new_peaks: Vec<F>,
+}
+
+impl<F: FromUniformBytes<64> + Ord> Mmr<F> {
+    pub fn new() -> Self {
+        Self { levels: vec![vec![]] }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    // sequence 'message' at the next index and return that index
+    pub fn append(&mut self, message: F) -> u64 {
+        let index = self.size();
+        self.levels[0].push(leaf_hash(index, message));
+
+        // merge the two right-most trees while they have the same height
+        let mut h = 0;
+        while self.levels[h].len() % 2 == 0 {
+            let len = self.levels[h].len();
+            let parent = hash_nodes(self.levels[h][len - 2], self.levels[h][len - 1]);
+            if h + 1 == self.levels.len() {
+                self.levels.push(vec![]);
+            }
+            self.levels[h + 1].push(parent);
+            h += 1;
+        }
+        index
+    }
+
+    pub fn peaks(&self, size: u64) -> Vec<F> {
+        assert!(size <= self.size(), "size exceeds the current log");
+        peak_positions(size)
+            .into_iter()
+            .map(|(h, offset)| self.levels[h][(offset >> h) as usize])
+            .collect()
+    }
+
+    pub fn root(&self) -> F {
+        self.root_at(self.size())
+    }
+
+    // root of the log when it had 'size' leaves
+    pub fn root_at(&self, size: u64) -> F {
+        mmr_root(size, &self.peaks(size))
+    }
+
+    // siblings from node (from, index >> from) up to height 'to'
+    fn path(&self, index: u64, from: usize, to: usize) -> Vec<F> {
+        (from..to)
+            .map(|h| self.levels[h][((index >> h) ^ 1) as usize])
+            .collect()
+    }
+
+    // inclusion proof of 'index' against the root of the log with 'size' leaves
+    pub fn prove(&self, index: u64, size: u64) -> Option<MmrProof<F>> {
+        if size > self.size() {
+            return None;
+        }
+        let (_, h) = peak_of(index, size)?;
+        Some(MmrProof { index, size, siblings: self.path(index, 0, h), peaks: self.peaks(size) })
+    }
+
+    pub fn prove_ancestry(&self, old_size: u64, new_size: u64) -> Option<AncestryProof<F>> {
+        if old_size > new_size || new_size > self.size() {
+            return None;
+        }
+        let paths = peak_positions(old_size)
+            .into_iter()
+            .map(|(h, offset)| {
+                let (_, new_h) = peak_of(offset, new_size).unwrap();
+                self.path(offset, h, new_h)
+            })
+            .collect();
+
+        Some(AncestryProof {
+            old_size,
+            new_size,
+            old_peaks: self.peaks(old_size),
+            paths,
+            new_peaks: self.peaks(new_size),
+        })
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord> Default for Mmr<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// climb from 'node' at height 'from' covering 'index'
+fn climb<F: FromUniformBytes<64> + Ord>(node: F, index: u64, from: usize, siblings: &[F]) -> F {
+    siblings.iter().enumerate().fold(node, |node, (l, sibling)| {
+        if (index >> (from + l)) & 1 == 0 {
+            hash_nodes(node, *sibling)
+        } else {
+            hash_nodes(*sibling, node)
+        }
+    })
+}
+
+impl<F: FromUniformBytes<64> + Ord> MmrProof<F> {
+    // 'message' was sequenced at 'index' in the log committed by 'root'
+    pub fn verify(&self, root: F, message: F) -> bool {
+        let (k, h) = match peak_of(self.index, self.size) {
+            Some(peak) => peak,
+            None => return false,
+        };
+        if self.siblings.len() != h || self.peaks.len() != self.size.count_ones() as usize {
+            return false;
+        }
+
+        let peak = climb(leaf_hash(self.index, message), self.index, 0, &self.siblings);
+        peak == self.peaks[k] && mmr_root(self.size, &self.peaks) == root
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord> AncestryProof<F> {
+    // the log committed by 'new_root' is an extension of the log committed by 'old_root'
+    pub fn verify(&self, old_root: F, new_root: F) -> bool {
+        if self.old_size > self.new_size
+            || self.old_peaks.len() != self.old_size.count_ones() as usize
+            || self.paths.len() != self.old_peaks.len()
+            || self.new_peaks.len() != self.new_size.count_ones() as usize
+        {
+            return false;
+        }
+        if mmr_root(self.old_size, &self.old_peaks) != old_root
+            || mmr_root(self.new_size, &self.new_peaks) != new_root
+        {
+            return false;
+        }
+
+        peak_positions(self.old_size)
+            .into_iter()
+            .zip(self.old_peaks.iter().zip(self.paths.iter()))
+            .all(|((h, offset), (old_peak, path))| {
+                let (k, new_h) = peak_of(offset, self.new_size).unwrap();
+                path.len() + h == new_h && climb(*old_peak, offset, h, path) == self.new_peaks[k]
+            })
+    }
+}
+
+
+#[test]
+pub fn test_mmr_inclusion() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut mmr = Mmr::<Base>::new();
+    let messages = (0..37).map(|_| Base::random(OsRng)).collect::<Vec<Base>>();
+    let mut roots = vec![mmr.root()];
+    for (i, m) in messages.iter().enumerate() {
+        assert_eq!(mmr.append(*m), i as u64);
+        roots.push(mmr.root());
+    }
+
+    // proofs against every historical root
+    for size in 1..=messages.len() as u64 {
+        for index in 0..size {
+            let proof = mmr.prove(index, size).unwrap();
+            assert!(proof.verify(roots[size as usize], messages[index as usize]));
+        }
+    }
+
+    // the leaf is bound to its index
+    let mut proof = mmr.prove(4, 37).unwrap();
+    assert!(!proof.verify(mmr.root(), messages[5]));
+    proof.index = 5;
+    assert!(!proof.verify(mmr.root(), messages[4]));
+    assert!(mmr.prove(37, 37).is_none());
+}
+
+#[test]
+pub fn test_mmr_ancestry() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut mmr = Mmr::<Base>::new();
+    let mut roots = vec![mmr.root()];
+    for _ in 0..33 {
+        mmr.append(Base::random(OsRng));
+        roots.push(mmr.root());
+    }
+
+    for old_size in 0..=33u64 {
+        for new_size in old_size..=33 {
+            let proof = mmr.prove_ancestry(old_size, new_size).unwrap();
+            assert!(proof.verify(roots[old_size as usize], roots[new_size as usize]));
+        }
+    }
+
+    // a log that rewrote index 3 is not an extension
+    let mut forked = Mmr::<Base>::new();
+    for i in 0..20 {
+        forked.append(if i == 3 { Base::random(OsRng) } else { Base::from(i) });
+    }
+    let mut honest = Mmr::<Base>::new();
+    for i in 0..8 {
+        honest.append(Base::from(i));
+    }
+    let proof = forked.prove_ancestry(8, 20).unwrap();
+    assert!(!proof.verify(honest.root(), forked.root()));
+    assert!(proof.verify(forked.root_at(8), forked.root()));
+}
@@ -0,0 +1,185 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::backend::{merkle_witness, CircuitShape, ProofSystem, Provable};
+use crate::merkle::InclusionProof;
+
+// Cost report of every circuit in the crate, under every enabled backend.
+//   cargo run --release -- report > report.json
+// Each entry is measured on a real witness : the minimum k comes from the MockProver, the proof
+// is produced and verified at that k, times are wall-clock milliseconds.
+// A circuit that can't be measured (no k fits, keygen or proving fails, the proof is rejected)
+// has an "error" instead of its costs, the other entries are still reported.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitReport {
+    pub circuit: String,
+    pub backend: &'static str,
+    #[serde(flatten)]
+    pub shape: CircuitShape,
+    #[serde(flatten)]
+    pub costs: Option<Costs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Costs {
+    pub min_k: u32,
+    pub proof_bytes: usize,
+    pub keygen_ms: f64,
+    pub prove_ms: f64,
+    pub verify_ms: f64,
+}
+
+fn millis(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+// 'empty' is the circuit without witnesses, used for keygen
+pub fn measure<P: ProofSystem, C: Provable<P>>(name: &str, empty: &C, circuit: &C, instances: &[Vec<P::Scalar>]) -> CircuitReport {
+    let (costs, error) = match costs::<P, C>(empty, circuit, instances) {
+        Ok(costs) => (Some(costs), None),
+        Err(error) => (None, Some(error)),
+    };
+    CircuitReport { circuit: name.to_string(), backend: P::NAME, shape: C::shape(), costs, error }
+}
+
+fn costs<P: ProofSystem, C: Provable<P>>(empty: &C, circuit: &C, instances: &[Vec<P::Scalar>]) -> Result<Costs, String> {
+    let min_k = circuit
+        .min_k(instances)
+        .ok_or_else(|| format!("the MockProver rejects the witness at every k up to {}", crate::backend::MAX_K))?;
+    let params = P::setup(min_k);
+
+    let now = Instant::now();
+    let pk = P::keygen(&params, empty).map_err(|e| format!("keygen failed: {:?}", e))?;
+    let keygen_ms = millis(now);
+
+    let now = Instant::now();
+    let proof = P::prove(&params, &pk, circuit, instances).map_err(|e| format!("proving failed: {:?}", e))?;
+    let prove_ms = millis(now);
+
+    let now = Instant::now();
+    if !P::verify(&params, P::vk(&pk), instances, &proof) {
+        return Err("the proof does not verify".to_string());
+    }
+    let verify_ms = millis(now);
+
+    Ok(Costs { min_k, proof_bytes: proof.len(), keygen_ms, prove_ms, verify_ms })
+}
+
+#[cfg(feature = "ipa-pasta")]
+fn ipa_reports() -> Vec<CircuitReport> {
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::pasta::pallas;
+    use halo2_pse::halo2curves::ff::Field;
+    use rand::rngs::OsRng;
+
+    use crate::backend::ipa::IpaPasta;
+    use crate::circuits::equivocation::{cosign, EquivocationCircuit, SignatureWitness};
+    use crate::circuits::schnorr::{public_key, sign, SchnorrCircuit};
+    use crate::circuits::merkle::MerkleCircuit;
+
+    let mut reports = vec![
+        merkle_report::<IpaPasta, _, 20>(MerkleCircuit::new),
+        merkle_report::<IpaPasta, _, 32>(MerkleCircuit::new),
+    ];
+
+    let secret = pallas::Scalar::random(OsRng);
+    let message = pallas::Base::random(OsRng);
+    let circuit = SchnorrCircuit::new(public_key(secret), message, sign(&mut OsRng, secret, message));
+    let instances = vec![SchnorrCircuit::instance(&public_key(secret), message)];
+    reports.push(measure::<IpaPasta, _>("schnorr", &SchnorrCircuit::default(), &circuit, &instances));
+
+    let index = pallas::Base::from(42);
+    let (m_1, m_2) = (pallas::Base::random(OsRng), pallas::Base::random(OsRng));
+    let first = cosign(&mut OsRng, pallas::Scalar::random(OsRng), secret, m_1, index);
+    let second = cosign(&mut OsRng, pallas::Scalar::random(OsRng), secret, m_2, index);
+    let circuit = EquivocationCircuit {
+        sequencer_key: Value::known(public_key(secret)),
+        index: Value::known(index),
+        first: SignatureWitness::new(m_1, &first),
+        second: SignatureWitness::new(m_2, &second),
+    };
+    let instances = vec![EquivocationCircuit::instance(&public_key(secret), index)];
+    reports.push(measure::<IpaPasta, _>("equivocation", &EquivocationCircuit::default(), &circuit, &instances));
+
+    reports
+}
+
+#[cfg(not(feature = "ipa-pasta"))]
+fn ipa_reports() -> Vec<CircuitReport> {
+    vec![]
+}
+
+#[cfg(feature = "kzg-bn254")]
+fn kzg_reports() -> Vec<CircuitReport> {
+    use crate::backend::kzg::{circuits::merkle::MerkleCircuit, KzgBn254};
+
+    vec![
+        merkle_report::<KzgBn254, _, 20>(MerkleCircuit::new),
+        merkle_report::<KzgBn254, _, 32>(MerkleCircuit::new),
+    ]
+}
+
+#[cfg(not(feature = "kzg-bn254"))]
+fn kzg_reports() -> Vec<CircuitReport> {
+    vec![]
+}
+
+// the Merkle circuit exists once per backend, 'new' picks the backend's copy
+fn merkle_report<P: ProofSystem, C: Provable<P> + Default, const DEPTH: usize>(
+    new: impl Fn(P::Scalar, &InclusionProof<P::Scalar, DEPTH>) -> C,
+) -> CircuitReport {
+    let (leaf, path, root) = merkle_witness::<P::Scalar, DEPTH>();
+    // public inputs : root, index
+    let instances = vec![vec![root, P::Scalar::from(path.index)]];
+    measure::<P, C>(&format!("merkle depth {}", DEPTH), &C::default(), &new(leaf, &path), &instances)
+}
+
+pub fn report_all() -> Vec<CircuitReport> {
+    let mut reports = ipa_reports();
+    reports.extend(kzg_reports());
+    reports
+}
+
+pub fn report_json() -> String {
+    serde_json::to_string_pretty(&report_all()).unwrap()
+}
+
+
+#[cfg(feature = "ipa-pasta")]
+#[test]
+pub fn test_circuit_report() {
+    use halo2_proofs::pasta::pallas;
+
+    use crate::backend::ipa::IpaPasta;
+    use crate::circuits::merkle::{k, MerkleCircuit};
+
+    let report = merkle_report::<IpaPasta, _, 4>(MerkleCircuit::new);
+    let costs = report.costs.unwrap();
+    assert!(costs.min_k <= k(4));
+    assert!(costs.proof_bytes > 0);
+    assert!(report.error.is_none());
+    assert_eq!(report.shape.advice_columns, 7);
+    assert_eq!(report.shape.instance_columns, 1);
+    assert_eq!(report.shape.lookups, 0);
+
+    // a witness the circuit rejects is reported as an error, not a panic
+    let (leaf, path, root) = merkle_witness::<_, 4>();
+    let wrong = vec![vec![root, pallas::Base::from(path.index + 1)]];
+    let failed = measure::<IpaPasta, _>("merkle depth 4", &MerkleCircuit::default(), &MerkleCircuit::<_, 4>::new(leaf, &path), &wrong);
+    assert!(failed.costs.is_none());
+    assert!(failed.error.is_some());
+
+    let json: serde_json::Value = serde_json::from_str(&serde_json::to_string(&[report, failed]).unwrap()).unwrap();
+    assert_eq!(json[0]["circuit"], "merkle depth 4");
+    assert_eq!(json[0]["backend"], "ipa-pasta");
+    assert_eq!(json[0]["advice_columns"], 7);
+    assert!(json[0]["min_k"].is_u64());
+    assert!(json[0].get("error").is_none());
+    assert!(json[1]["error"].is_string());
+    assert!(json[1].get("min_k").is_none());
+    assert_eq!(json[1]["advice_columns"], 7);
+}
@@ -1,14 +1,15 @@
 
 use halo2_proofs::arithmetic::Field;
 use halo2_pse::plonk::Challenge;
-use halo2curves::CurveAffine;
-use halo2curves::bn256::G1 as Point;
-use halo2curves::bn256::G1Affine as PointAffine;
-use halo2curves::bn256::Fr as Scalar;
-use halo2curves::bn256::Fq as Base;
-
-use halo2curves::ff::PrimeField;
-use halo2curves::group::Group;
+use halo2_pse::halo2curves::CurveAffine;
+use halo2_pse::halo2curves::bn256::G1 as Point;
+use halo2_pse::halo2curves::bn256::G1Affine as PointAffine;
+use halo2_pse::halo2curves::bn256::Fr as Scalar;
+use halo2_pse::halo2curves::bn256::Fq as Base;
+
+use halo2_pse::halo2curves::ff::PrimeField;
+use halo2_pse::halo2curves::group::Curve;
+use halo2_pse::halo2curves::group::Group;
 use rand::CryptoRng;
 use rand::RngCore;
 use rand::rngs::OsRng;
@@ -50,6 +51,12 @@ impl PublicKey {
         let p = nk_generator() * s.inner();
         Self(p)
     }
+
+    // aggregated key X = w_u * P_u + w_s * P_s of a user and the sequencer
+    pub fn aggregate(user: &PublicKey, sequencer: &PublicKey) -> Self {
+        let (w_u, w_s) = musig_coefficients(&user.inner().to_affine(), &sequencer.inner().to_affine());
+        Self(user.inner() * mod_r_p(w_u) + sequencer.inner() * mod_r_p(w_s))
+    }
 }
 
 
@@ -94,16 +101,35 @@ pub fn mod_r_p(x: Base) -> Scalar {
     Scalar::from_repr(x.to_repr()).unwrap()
 }
 
+// MuSig key coefficients : L = H(P_u || P_s), w_u = H(L || P_u), w_s = H(L || P_s)
+pub fn musig_coefficients(user: &PointAffine, sequencer: &PointAffine) -> (Base, Base) {
+    let l = poseidon_hash(vec![user.x, user.y, sequencer.x, sequencer.y]);
+    let w_u = poseidon_hash(vec![l, user.x, user.y]);
+    let w_s = poseidon_hash(vec![l, sequencer.x, sequencer.y]);
+    (w_u, w_s)
+}
+
+// challenge e = H(R || m) of a plain signature
+fn challenge(commit: &Point, message: &[u8;32]) -> Base {
+    let message_base = Base::from_bytes(message.into()).unwrap();
+    poseidon_hash(vec![commit.x, commit.y, message_base])
+}
+
+// challenge e = H(R || X || m || i) of a signature under the aggregated key X, committing m to index i
+pub fn aggregated_challenge(commit: &Point, key: &PublicKey, message: &[u8;32], index: u64) -> Base {
+    let message_base = Base::from_bytes(message.into()).unwrap();
+    let (commit, key) = (commit.to_affine(), key.inner().to_affine());
+    poseidon_hash(vec![commit.x, commit.y, key.x, key.y, message_base, Base::from(index)])
+}
+
 impl SchnorrSecret for SecretKey {
     fn sign(&self, rng: &mut (impl CryptoRng + RngCore), message: &[u8;32]) -> Signature {
         //todo!()
         let mask = Scalar::random(rng);
         let commit = nk_generator() * mask; //  G1Affine (Base??) * Fr (Scalar)
 
-        let message_base = Base::from_bytes(message.into()).unwrap();
-
         // generate a challenge e
-        let challenge = poseidon_hash(vec![commit.x, commit.y, message_base]);
+        let challenge = challenge(&commit, message);
         let response = mask + mod_r_p(challenge) * self.inner();
 
         Signature { commit, response }
@@ -126,9 +152,7 @@ impl SchnorrSecret for SecretKey {
 impl SchnorrPublic for PublicKey {
     fn verify(&self, message: &[u8;32], signature: &Signature) -> bool {
 
-        let message_base = Base::from_bytes(message.into()).unwrap();
-
-        let challenge = poseidon_hash(vec![signature.commit.x, signature.commit.y, message_base ]);
+        let challenge = challenge(&signature.commit, message);
         nk_generator() * signature.response - self.inner() * mod_r_p(challenge) == signature.commit
     }
     // public_nonce: Base is replaced by commit : nonce * G
@@ -140,6 +164,111 @@ impl SchnorrPublic for PublicKey {
     }
 }
 
+
+// == Half aggregation == //
+// Every signature (R_i, s_i) here satisfies s_i * G == R_i + e_i * K_i, where
+//  - plain signature      : K_i = P, e_i = H(R_i || m_i)
+//  - aggregated signature : K_i = X, e_i = H(R_i || X || m_i || i), X = w_u * P_u + w_s * P_s
+// Half aggregation keeps every R_i and folds the responses : s = sum z_i * s_i,
+// with z_i = H(L || i) and L = H(R_1 || K_1 || e_1 || ... || R_n || K_n || e_n).
+// The verifier only gets the entries below : every e_i is recomputed from the R_i of the
+// aggregate, and the aggregate is checked with s * G == sum z_i * (R_i + e_i * K_i).
+
+// the signer and the message of one signature
+#[derive(Clone, Copy)]
+pub enum HalfAggEntry<'a> {
+    Plain(&'a PublicKey, &'a [u8;32]),
+    // aggregated key X, message and the index it was committed to
+    Aggregated(&'a PublicKey, &'a [u8;32], u64),
+}
+
+impl HalfAggEntry<'_> {
+    // verification key K and challenge e for the commit R, None if the message isn't a field element
+    fn key_and_challenge(&self, commit: &Point) -> Option<(Point, Base)> {
+        match *self {
+            HalfAggEntry::Plain(public, message) => {
+                Option::<Base>::from(Base::from_bytes(message))?;
+                Some((public.inner(), challenge(commit, message)))
+            }
+            HalfAggEntry::Aggregated(key, message, index) => {
+                Option::<Base>::from(Base::from_bytes(message))?;
+                Some((key.inner(), aggregated_challenge(commit, key, message, index)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HalfAggregate {
+    commits : Vec<Point>,
+    response : Scalar,
+}
+
+// (K_i, e_i) of every entry, None if a message isn't a field element
+fn half_agg_keys(entries: &[HalfAggEntry], commits: &[Point]) -> Option<Vec<(Point, Base)>> {
+    entries.iter().zip(commits.iter()).map(|(entry, commit)| entry.key_and_challenge(commit)).collect()
+}
+
+// hash-derived coefficients z_i binding every (R_i, K_i, e_i) of the batch
+fn half_agg_coefficients(keys: &[(Point, Base)], commits: &[Point]) -> Vec<Scalar> {
+    let mut transcript = vec![];
+    for ((key, challenge), commit) in keys.iter().zip(commits.iter()) {
+        let commit = commit.to_affine();
+        let key = key.to_affine();
+        transcript.extend_from_slice(&[commit.x, commit.y, key.x, key.y, *challenge]);
+    }
+    let l = poseidon_hash(transcript);
+
+    (0..keys.len())
+        .map(|i| mod_r_p(poseidon_hash(vec![l, Base::from(i as u64)])))
+        .collect()
+}
+
+// None if the batch is empty, the lengths differ or a message isn't a field element
+pub fn half_aggregate(entries: &[HalfAggEntry], signatures: &[Signature]) -> Option<HalfAggregate> {
+    if entries.is_empty() || entries.len() != signatures.len() {
+        return None;
+    }
+
+    let commits = signatures.iter().map(|sig| sig.commit).collect::<Vec<Point>>();
+    let keys = half_agg_keys(entries, &commits)?;
+    let response = half_agg_coefficients(&keys, &commits)
+        .iter()
+        .zip(signatures.iter())
+        .fold(Scalar::zero(), |acc, (z, sig)| acc + *z * sig.response);
+
+    Some(HalfAggregate { commits, response })
+}
+
+impl HalfAggregate {
+    pub fn len(&self) -> usize {
+        self.commits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commits.is_empty()
+    }
+
+    // 'entries' are the signers and messages, in the order they were aggregated
+    pub fn verify(&self, entries: &[HalfAggEntry]) -> bool {
+        if entries.is_empty() || entries.len() != self.commits.len() {
+            return false;
+        }
+        let keys = match half_agg_keys(entries, &self.commits) {
+            Some(keys) => keys,
+            None => return false,
+        };
+
+        let right = half_agg_coefficients(&keys, &self.commits)
+            .iter()
+            .zip(keys.iter().zip(self.commits.iter()))
+            .fold(Point::identity(), |acc, (z, ((key, challenge), commit))| {
+                acc + (*commit + *key * mod_r_p(*challenge)) * *z
+            });
+        nk_generator() * self.response == right
+    }
+}
+
 #[test]
 fn test_schnorr_sig() {
     let secret = SecretKey::random(&mut OsRng);
@@ -198,3 +327,125 @@ fn sign_ext(&self, rng: &mut (impl CryptoRng + RngCore), message: &[u8;32],
     assert_eq!(left, right)
 
 }
+
+
+#[test]
+fn test_half_aggregation() {
+    let mut publics = vec![];
+    let mut messages = vec![];
+    let mut signatures = vec![];
+
+    // plain signatures from different users
+    for j in 0..5u8 {
+        let secret = SecretKey::random(&mut OsRng);
+        let message = [j;32];
+        signatures.push(secret.sign(&mut OsRng, &message));
+        publics.push(PublicKey::from_secret(secret));
+        messages.push(message);
+    }
+    let entries = publics
+        .iter()
+        .zip(messages.iter())
+        .map(|(public, message)| HalfAggEntry::Plain(public, message))
+        .collect::<Vec<HalfAggEntry>>();
+
+    let aggregate = half_aggregate(&entries, &signatures).unwrap();
+    assert_eq!(aggregate.len(), 5);
+    assert!(aggregate.verify(&entries));
+
+    // tampering with any single signature, key or message is detected
+    let other_message = [9u8;32];
+    for i in 0..signatures.len() {
+        let mut tampered = signatures.clone();
+        tampered[i].response += Scalar::one();
+        assert!(!half_aggregate(&entries, &tampered).unwrap().verify(&entries));
+
+        let mut tampered = signatures.clone();
+        tampered[i].commit += nk_generator();
+        assert!(!half_aggregate(&entries, &tampered).unwrap().verify(&entries));
+
+        let mut wrong_entries = entries.clone();
+        wrong_entries[i] = HalfAggEntry::Plain(&publics[i], &other_message);
+        assert!(!aggregate.verify(&wrong_entries));
+
+        let mut wrong_entries = entries.clone();
+        wrong_entries[i] = HalfAggEntry::Plain(&publics[(i + 1) % publics.len()], &messages[i]);
+        assert!(!aggregate.verify(&wrong_entries));
+    }
+
+    // the order of the batch is bound
+    let mut swapped = entries.clone();
+    swapped.swap(0, 1);
+    assert!(!aggregate.verify(&swapped));
+    assert!(!aggregate.verify(&entries[1..]));
+
+    // a message that isn't a field element is rejected, not a panic
+    let mut wrong_entries = entries.clone();
+    let too_large = [0xff;32];
+    wrong_entries[0] = HalfAggEntry::Plain(&publics[0], &too_large);
+    assert!(!aggregate.verify(&wrong_entries));
+    assert!(half_aggregate(&wrong_entries, &signatures).is_none());
+}
+
+#[test]
+fn test_half_aggregation_aggregated() {
+    let sequencer = SecretKey::random(&mut OsRng);
+    let mut keys = vec![];
+    let mut messages = vec![];
+    let mut signatures = vec![];
+
+    // receipts co-signed by a user and the sequencer, message j committed to index 10 + j
+    for j in 0..4u8 {
+        let user = SecretKey::random(&mut OsRng);
+        let (p_u, p_s) = (PublicKey::from_secret(user.clone()), PublicKey::from_secret(sequencer.clone()));
+        let (w_u, w_s) = musig_coefficients(&p_u.inner().to_affine(), &p_s.inner().to_affine());
+        let key = PublicKey::aggregate(&p_u, &p_s);
+
+        let message = [j;32];
+        let (r_u, r_s) = (Scalar::random(OsRng), Scalar::random(OsRng));
+        let commit = nk_generator() * r_u + nk_generator() * r_s;
+        let e = aggregated_challenge(&commit, &key, &message, 10 + j as u64);
+        let signature_u = user.sign_ext(&mut OsRng, &message, r_u, w_u, e);
+        let signature_s = sequencer.sign_ext(&mut OsRng, &message, r_s, w_s, e);
+        let signature = Signature { commit, response: signature_u.response + signature_s.response };
+        assert!(key.verify_ext(&message, &signature, Base::one(), e));
+
+        keys.push(key);
+        messages.push(message);
+        signatures.push(signature);
+    }
+
+    // mixed with a plain signature
+    let secret = SecretKey::random(&mut OsRng);
+    let plain_message = [7u8;32];
+    signatures.push(secret.sign(&mut OsRng, &plain_message));
+    let public = PublicKey::from_secret(secret);
+
+    let mut entries = keys
+        .iter()
+        .zip(messages.iter())
+        .enumerate()
+        .map(|(j, (key, message))| HalfAggEntry::Aggregated(key, message, 10 + j as u64))
+        .collect::<Vec<HalfAggEntry>>();
+    entries.push(HalfAggEntry::Plain(&public, &plain_message));
+
+    let aggregate = half_aggregate(&entries, &signatures).unwrap();
+    assert_eq!(aggregate.len(), 5);
+    assert!(aggregate.verify(&entries));
+
+    // the index is bound : the same receipt can't be claimed for another slot
+    for i in 0..keys.len() {
+        let mut wrong_entries = entries.clone();
+        wrong_entries[i] = HalfAggEntry::Aggregated(&keys[i], &messages[i], 11 + i as u64);
+        assert!(!aggregate.verify(&wrong_entries));
+
+        // nor checked as a plain signature under X
+        let mut wrong_entries = entries.clone();
+        wrong_entries[i] = HalfAggEntry::Plain(&keys[i], &messages[i]);
+        assert!(!aggregate.verify(&wrong_entries));
+
+        let mut tampered = signatures.clone();
+        tampered[i].response += Scalar::one();
+        assert!(!half_aggregate(&entries, &tampered).unwrap().verify(&entries));
+    }
+}
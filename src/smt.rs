@@ -0,0 +1,249 @@
+use std::collections::{BTreeSet, HashMap};
+
+use halo2_pse::halo2curves::ff::FromUniformBytes;
+
+use crate::merkle::{hash_nodes, zero_hashes};
+
+// Poseidon sparse Merkle tree keyed by the sequence index i.
+// Leaf i holds the message hash committed to index i, an empty slot holds zero.
+// A membership proof shows "index i holds m", a non-membership proof shows "index i is empty",
+// and both use the same path : the verifier only changes the leaf value.
+
+pub const SMT_DEPTH: usize = 64;
+
+// position of the node covering 'key' at height h
+fn position(key: u64, h: usize) -> u64 {
+    if h >= 64 { 0 } else { key >> h }
+}
+
+#[derive(Debug, Clone)]
+pub struct SparseMerkleTree<F> {
+    nodes: HashMap<(usize, u64), F>,    // non-empty nodes only : (height, position) -> node
+    zeros: Vec<F>,
+}
+
+// siblings[h] is the sibling at height h on the path of 'key'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtProof<F> {
+    pub key: u64,
+    pub siblings: Vec<F>,
+}
+
+impl<F: FromUniformBytes<64> + Ord> SparseMerkleTree<F> {
+    pub fn new() -> Self {
+        Self { nodes: HashMap::new(), zeros: zero_hashes(SMT_DEPTH) }
+    }
+
+    fn node(&self, h: usize, pos: u64) -> F {
+        self.nodes.get(&(h, pos)).copied().unwrap_or(self.zeros[h])
+    }
+
+    fn set_node(&mut self, h: usize, pos: u64, value: F) {
+        if value == self.zeros[h] {
+            self.nodes.remove(&(h, pos));
+        } else {
+            self.nodes.insert((h, pos), value);
+        }
+    }
+
+    pub fn root(&self) -> F {
+        self.node(SMT_DEPTH, 0)
+    }
+
+    // tx hash at index 'key', zero if the slot is empty
+    pub fn get(&self, key: u64) -> F {
+        self.node(0, key)
+    }
+
+    // setting a zero value clears the slot
+    pub fn insert(&mut self, key: u64, value: F) {
+        self.update_batch(&[(key, value)]);
+    }
+
+    // Write all leaves first, then rehash each touched node once per level.
+    // If a key appears more than once the last value wins.
+    pub fn update_batch(&mut self, updates: &[(u64, F)]) {
+        let mut dirty = BTreeSet::new();
+        for (key, value) in updates {
+            self.set_node(0, *key, *value);
+            dirty.insert(*key);
+        }
+
+        for h in 0..SMT_DEPTH {
+            let parents = dirty.iter().map(|pos| pos >> 1).collect::<BTreeSet<u64>>();
+            for parent in parents.iter() {
+                let left = self.node(h, parent << 1);
+                let right = self.node(h, (parent << 1) | 1);
+                self.set_node(h + 1, *parent, hash_nodes(left, right));
+            }
+            dirty = parents;
+        }
+    }
+
+    pub fn prove(&self, key: u64) -> SmtProof<F> {
+        let siblings = (0..SMT_DEPTH)
+            .map(|h| self.node(h, position(key, h) ^ 1))
+            .collect();
+        SmtProof { key, siblings }
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord> Default for SparseMerkleTree<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FromUniformBytes<64> + Ord> SmtProof<F> {
+    pub fn compute_root(&self, value: F) -> F {
+        let mut node = value;
+        for (h, sibling) in self.siblings.iter().enumerate() {
+            node = if position(self.key, h) & 1 == 0 {
+                hash_nodes(node, *sibling)
+            } else {
+                hash_nodes(*sibling, node)
+            };
+        }
+        node
+    }
+
+    // index 'key' holds 'value'
+    pub fn verify_membership(&self, root: F, value: F) -> bool {
+        value != F::ZERO
+            && self.siblings.len() == SMT_DEPTH
+            && self.compute_root(value) == root
+    }
+
+    // index 'key' is empty
+    pub fn verify_non_membership(&self, root: F) -> bool {
+        self.siblings.len() == SMT_DEPTH && self.compute_root(F::ZERO) == root
+    }
+
+    // Compact encoding : key (8 bytes LE) || bitmap (8 bytes LE) || non-default siblings (32 bytes each).
+    // Bit h of the bitmap is set when siblings[h] differs from the empty subtree of height h,
+    // so a proof in a sparse tree only carries the few siblings that are actually populated.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let zeros = zero_hashes::<F>(SMT_DEPTH);
+        let mut bitmap = 0u64;
+        let mut body = vec![];
+        for (h, sibling) in self.siblings.iter().enumerate() {
+            if *sibling != zeros[h] {
+                bitmap |= 1 << h;
+                body.extend_from_slice(sibling.to_repr().as_ref());
+            }
+        }
+
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&self.key.to_le_bytes());
+        bytes.extend_from_slice(&bitmap.to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 16 {
+            return None;
+        }
+        let key = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let bitmap = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+
+        let repr_len = F::Repr::default().as_ref().len();
+        let body = &bytes[16..];
+        if body.len() != bitmap.count_ones() as usize * repr_len {
+            return None;
+        }
+
+        let zeros = zero_hashes::<F>(SMT_DEPTH);
+        let mut chunks = body.chunks(repr_len);
+        let mut siblings = Vec::with_capacity(SMT_DEPTH);
+        for (h, zero) in zeros.iter().take(SMT_DEPTH).enumerate() {
+            if (bitmap >> h) & 1 == 1 {
+                let mut repr = F::Repr::default();
+                repr.as_mut().copy_from_slice(chunks.next()?);
+                siblings.push(Option::from(F::from_repr(repr))?);
+            } else {
+                siblings.push(*zero);
+            }
+        }
+        Some(Self { key, siblings })
+    }
+}
+
+
+#[test]
+pub fn test_smt_membership() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = SparseMerkleTree::<Base>::new();
+    let empty_root = tree.root();
+    let m_0 = Base::random(OsRng);
+    let m_7 = Base::random(OsRng);
+    tree.insert(0, m_0);
+    tree.insert(7, m_7);
+    tree.insert(u64::MAX, m_7);
+    let root = tree.root();
+
+    let proof = tree.prove(7);
+    assert!(proof.verify_membership(root, m_7));
+    assert!(!proof.verify_membership(root, m_0));
+    assert!(!proof.verify_non_membership(root));
+
+    let proof = tree.prove(u64::MAX);
+    assert!(proof.verify_membership(root, m_7));
+
+    let proof = tree.prove(8);
+    assert!(proof.verify_non_membership(root));
+    assert!(!proof.verify_membership(root, m_7));
+
+    // clearing slots restores the empty tree
+    tree.insert(0, Base::zero());
+    tree.insert(7, Base::zero());
+    tree.insert(u64::MAX, Base::zero());
+    assert_eq!(tree.root(), empty_root);
+}
+
+#[test]
+pub fn test_smt_batch_update() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let updates = (0..32u64)
+        .map(|i| (i * 3, Base::random(OsRng)))
+        .collect::<Vec<(u64, Base)>>();
+
+    let mut sequential = SparseMerkleTree::<Base>::new();
+    for (key, value) in updates.iter() {
+        sequential.insert(*key, *value);
+    }
+    let mut batched = SparseMerkleTree::<Base>::new();
+    batched.update_batch(&updates);
+
+    assert_eq!(sequential.root(), batched.root());
+    for (key, value) in updates.iter() {
+        assert!(batched.prove(*key).verify_membership(batched.root(), *value));
+    }
+}
+
+#[test]
+pub fn test_smt_proof_serialization() {
+    use halo2_pse::halo2curves::bn256::Fq as Base;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    let mut tree = SparseMerkleTree::<Base>::new();
+    tree.update_batch(&[(1, Base::random(OsRng)), (2, Base::random(OsRng)), (1 << 40, Base::random(OsRng))]);
+
+    for key in [1, 2, 3, 1 << 40] {
+        let proof = tree.prove(key);
+        let bytes = proof.to_bytes();
+        // only the populated siblings are encoded
+        assert!(bytes.len() <= 16 + 3 * 32);
+        assert_eq!(SmtProof::<Base>::from_bytes(&bytes).unwrap(), proof);
+    }
+
+    let bytes = tree.prove(1).to_bytes();
+    assert!(SmtProof::<Base>::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+}